@@ -1,5 +1,7 @@
 mod plugins;
 
+use std::time::Duration;
+
 use bevy::{
     asset::{AssetServer, HandleUntyped},
     ecs::world::{Mut, World},
@@ -8,9 +10,9 @@ use bevy::{
         mouse::{MouseButtonInput, MouseMotion},
     },
     prelude::{
-        default, info, App, Assets, Camera2dBundle, ClearColor, Color, Commands, CoreStage, Entity,
-        EventReader, Handle, Image, ImagePlugin, IntoSystemDescriptor, PluginGroup, ResMut,
-        Resource, State, SystemLabel, Vec2,
+        debug, default, info, App, Assets, Camera2dBundle, ClearColor, Color, Commands, CoreStage,
+        Entity, EventReader, Handle, Image, ImagePlugin, IntoSystemDescriptor, PluginGroup, Res,
+        ResMut, Resource, State, SystemLabel, Vec2,
     },
     sprite::TextureAtlas,
     text::Font,
@@ -20,39 +22,22 @@ use bevy::{
     DefaultPlugins,
 };
 use bevy_asset_loader::prelude::{AssetCollection, LoadingState, LoadingStateAppExt};
-use durakifa_protocol::protocol::{Authorize, Protocol};
+use durakifa_protocol::protocol::{self, Authorize, NameError, Protocol, TICK_DEFAULT};
 use naia_bevy_client::{
     events::{DespawnEntityEvent, MessageEvent, SpawnEntityEvent},
-    shared::{DefaultChannels, SharedConfig},
+    shared::DefaultChannels,
     Client, ClientConfig, Plugin as ClientPlugin, Stage,
 };
-use obfstr::obfstr;
+use naia_client::Client as NaiaClient;
 use plugins::{
-    dimensions::DimensionsPlugin, load::LoadPlugin, lobby::LobbyPlugin, menu::MenuPlugin,
-    mouse::MousePlugin, register::RegisterPlugin, room::RoomPlugin, vkeyboard::VKeyboardPlugin,
+    chat::ChatPlugin, code::CodePlugin, dimensions::DimensionsPlugin, filter::FilterPlugin,
+    leaderboard::LeaderboardPlugin, load::LoadPlugin, lobby::LobbyPlugin, menu::MenuPlugin,
+    mouse::MousePlugin, new_room::NewRoomPlugin, quick_play::QuickPlayPlugin,
+    reconnect::ReconnectPlugin, register::RegisterPlugin, room::RoomPlugin, server::ServerPlugin,
+    toast::ToastPlugin, vkeyboard::VKeyboardPlugin,
 };
 use wasm_bindgen::prelude::wasm_bindgen;
 
-#[cfg(debug_assertions)]
-const SRV_ADDR: &str = "127.0.0.1";
-#[cfg(not(debug_assertions))]
-const SRV_ADDR: &str = env!("SRV_ADDR");
-
-#[cfg(not(debug_assertions))]
-const SRV_KEY: &str = env!("SRV_KEY");
-#[cfg(debug_assertions)]
-const SRV_KEY: &str = "SRV_KEY";
-
-#[cfg(debug_assertions)]
-const SRV_PORT: &str = "55500";
-#[cfg(not(debug_assertions))]
-const SRV_PORT: &str = env!("SRV_PORT");
-
-#[cfg(debug_assertions)]
-const SRV_PROT: &str = "http";
-#[cfg(not(debug_assertions))]
-const SRV_PROT: &str = env!("SRV_PROT");
-
 const WND_CLR: Color = Color::BLACK;
 const WND_SZE_MIN_X: f32 = 200.0;
 const WND_SZE_MIN_Y: f32 = 220.0;
@@ -62,12 +47,20 @@ const WND_TTL: &str = "DURAKIFA!";
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 enum AppState {
+    Chat,
+    Code,
     Connect,
+    Filter,
     Game,
+    Leaderboard,
     Load,
     Lobby,
+    NewRoom,
+    QuickPlay,
+    Reconnect,
     Register,
     Room,
+    Server,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -97,6 +90,42 @@ enum NetState {
 #[derive(Default, Resource)]
 struct LocalUser {
     entity: Option<Entity>,
+    /// Set once the server dropped the session of the idle user, it is not resumed then.
+    idle: bool,
+    name: String,
+    /// Why the server refused the name, it keeps no session for it.
+    name_error: Option<NameError>,
+    password: Option<String>,
+    rejected: bool,
+    room: Option<Entity>,
+    sign_up: bool,
+    token: Option<String>,
+}
+
+#[derive(Default, Resource)]
+struct ServerChoice {
+    url: String,
+}
+
+/// The naia client can only take on the tick of the server while it is disconnected.
+#[derive(Resource)]
+struct Tick {
+    client: Duration,
+    server: Duration,
+    /// Set while the client reconnects to take on the tick the server told.
+    switching: bool,
+}
+
+impl Tick {
+    fn apply(&mut self, commands: &mut Commands) {
+        if self.server != self.client {
+            self.client = self.server;
+            commands.insert_resource(NaiaClient::<Protocol, Entity, DefaultChannels>::new(
+                &ClientConfig::default(),
+                &protocol::shared_config(self.client),
+            ));
+        }
+    }
 }
 
 #[derive(AssetCollection, Resource)]
@@ -115,16 +144,55 @@ fn cleanup(
     }
 }
 
-fn connect(mut net_state: ResMut<State<NetState>>) {
+fn connect(mut net_state: ResMut<State<NetState>>, mut tick: ResMut<Tick>) {
+    tick.switching = false;
     if vec![NetState::Offline].contains(net_state.current()) {
         net_state.set(NetState::Online).unwrap();
     }
 }
 
-fn disconnect(mut net_state: ResMut<State<NetState>>) {
+fn disconnect(
+    mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    mut local_user: ResMut<LocalUser>,
+    mut net_state: ResMut<State<NetState>>,
+    mut tick: ResMut<Tick>,
+) {
     if vec![NetState::Online].contains(net_state.current()) {
         net_state.set(NetState::Offline).unwrap();
     }
+
+    // The server sends a fresh OwnUser once the session has been resumed
+    local_user.entity = None;
+    local_user.room = None;
+    if local_user.name_error.is_some() {
+        return;
+    }
+
+    if vec![
+        AppState::Chat,
+        AppState::Code,
+        AppState::Filter,
+        AppState::Game,
+        AppState::Leaderboard,
+        AppState::Lobby,
+        AppState::NewRoom,
+        AppState::QuickPlay,
+        AppState::Register,
+        AppState::Room,
+    ]
+    .contains(app_state.current())
+    {
+        // Taking on the tick of the server loses no connection
+        let state = match (local_user.idle, tick.switching) {
+            (true, _) => AppState::Register,
+            (false, true) => AppState::Connect,
+            (false, false) => AppState::Reconnect,
+        };
+        app_state.overwrite_set(state).unwrap();
+    }
+
+    tick.apply(&mut commands);
 }
 
 fn input_keyboard(mut input: EventReader<KeyboardInput>, mut state: ResMut<InputState>) {
@@ -145,11 +213,60 @@ fn input_mouse(
     }
 }
 
-fn setup(mut client: Client<Protocol, DefaultChannels>, mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+fn open_connection(
+    client: &mut Client<Protocol, DefaultChannels>,
+    local_user: &LocalUser,
+    server: &ServerChoice,
+) {
+    // Disconnecting resets the handshake, so it has to come before the new auth
+    if client.is_connected() {
+        client.disconnect();
+    }
+
+    client.auth(Authorize::new(
+        local_user.name.clone(),
+        local_user.password.clone(),
+        local_user.sign_up,
+        local_user.token.clone(),
+    ));
 
-    client.auth(Authorize::new(obfstr!(SRV_KEY).to_string()));
-    client.connect(&format!("{}://{}:{}", SRV_PROT, SRV_ADDR, SRV_PORT));
+    // naia keeps retrying a handshake that is under way and panics on a second connect
+    if !client.is_connecting() {
+        client.connect(&server.url);
+    }
+}
+
+fn refuse_name(
+    mut app_state: ResMut<State<AppState>>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut local_user: ResMut<LocalUser>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::NameRejected(msg)) = event {
+            local_user.name_error = Some((*msg.reason).clone());
+            client.disconnect();
+            app_state.overwrite_set(AppState::Register).unwrap();
+            return;
+        }
+    }
+}
+
+fn reject(
+    mut app_state: ResMut<State<AppState>>,
+    mut local_user: ResMut<LocalUser>,
+    tick: Res<Tick>,
+) {
+    // While reconnecting or taking on the tick, a rejection just means the old session has not
+    // timed out yet
+    if !tick.switching && vec![AppState::Connect].contains(app_state.current()) {
+        local_user.rejected = true;
+        app_state.set(AppState::Register).unwrap();
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
 }
 
 #[wasm_bindgen]
@@ -158,9 +275,15 @@ pub fn start() {
         .insert_resource(ClearColor(WND_CLR))
         .insert_resource(InputState::Mouse)
         .insert_resource(LocalUser::default())
+        .insert_resource(ServerChoice::default())
+        .insert_resource(Tick {
+            client: TICK_DEFAULT,
+            server: TICK_DEFAULT,
+            switching: false,
+        })
         .add_loading_state(
             LoadingState::new(AppState::Load)
-                .continue_to_state(AppState::Connect)
+                .continue_to_state(AppState::Server)
                 .with_collection::<FontAssets>()
                 .with_collection::<ImageAssets>()
                 .with_collection::<SpriteSheetAssets>(),
@@ -186,51 +309,94 @@ pub fn start() {
         )
         .add_plugin(ClientPlugin::<Protocol, DefaultChannels>::new(
             ClientConfig::default(),
-            SharedConfig::default(),
+            protocol::shared_config(TICK_DEFAULT),
         ))
+        .add_plugin(ChatPlugin)
+        .add_plugin(CodePlugin)
         .add_plugin(DimensionsPlugin)
+        .add_plugin(FilterPlugin)
+        .add_plugin(LeaderboardPlugin)
         .add_plugin(LoadPlugin)
         .add_plugin(LobbyPlugin)
         .add_plugin(MenuPlugin)
         .add_plugin(MousePlugin)
+        .add_plugin(NewRoomPlugin)
+        .add_plugin(QuickPlayPlugin)
+        .add_plugin(ReconnectPlugin)
         .add_plugin(RegisterPlugin)
         .add_plugin(RoomPlugin)
+        .add_plugin(ServerPlugin)
+        .add_plugin(ToastPlugin)
         .add_plugin(VKeyboardPlugin)
         .add_startup_system(setup)
         .add_state(AppState::Load)
         .add_state(NetState::Offline)
         .add_system(input_keyboard.label(InputState::Keyboard))
         .add_system(input_mouse.label(InputState::Mouse))
+        .add_system(switch_tick)
         .add_system_to_stage(CoreStage::PostUpdate, cleanup)
         .add_system_to_stage(Stage::Connection, connect)
         .add_system_to_stage(Stage::Disconnection, disconnect)
+        .add_system_to_stage(Stage::Rejection, reject)
         .add_system_to_stage(Stage::ReceiveEvents, debug_despawn)
         .add_system_to_stage(Stage::ReceiveEvents, debug_spawn)
+        .add_system_to_stage(Stage::ReceiveEvents, refuse_name)
         .add_system_to_stage(Stage::ReceiveEvents, update_local_player)
         .run();
 }
 
+fn switch_tick(
+    mut client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    net_state: Res<State<NetState>>,
+    server: Res<ServerChoice>,
+    tick: Res<Tick>,
+) {
+    // The rebuilt client resumes the session the old one was given
+    if tick.switching
+        && vec![NetState::Offline].contains(net_state.current())
+        && !client.is_connecting()
+    {
+        open_connection(&mut client, &local_user, &server);
+    }
+}
+
 fn update_local_player(
-    client: Client<Protocol, DefaultChannels>,
+    mut client: Client<Protocol, DefaultChannels>,
     mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
     mut local_user: ResMut<LocalUser>,
+    server: Res<ServerChoice>,
+    mut tick: ResMut<Tick>,
 ) {
     for event in event_reader.iter() {
         if let MessageEvent(_, Protocol::OwnUser(msg)) = event {
             local_user.entity = msg.user.get(&client);
+            local_user.room = msg.room.get(&client);
+            local_user.token = Some((*msg.token).clone());
+            // The account exists now, logging in again after the session expired signs in
+            local_user.sign_up = false;
             info!("local user: {:?}", local_user.entity);
+
+            // Comes back with the token once the naia client has been rebuilt for the tick
+            tick.server = Duration::from_millis(*msg.tick);
+            if tick.server != tick.client {
+                plugins::server::remember_tick(&server.url, tick.server);
+                tick.switching = true;
+                client.disconnect();
+                return;
+            }
         }
     }
 }
 
 fn debug_despawn(mut event_reader: EventReader<DespawnEntityEvent>) {
     for event in event_reader.iter() {
-        info!("despawned {:?}", event.0);
+        debug!("despawned {:?}", event.0);
     }
 }
 
 fn debug_spawn(mut event_reader: EventReader<SpawnEntityEvent>) {
     for event in event_reader.iter() {
-        info!("spawned {:?}", event.0);
+        debug!("spawned {:?}", event.0);
     }
 }
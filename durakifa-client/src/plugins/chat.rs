@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    input::Input,
+    math::Vec2,
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, KeyCode, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, Transform, With, Without,
+    },
+    text::{Text, Text2dBounds, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+use durakifa_protocol::protocol::{Protocol, SendChat};
+use naia_bevy_client::{events::MessageEvent, shared::DefaultChannels, Client};
+
+use crate::{AppState, FontAssets, InputState};
+
+use super::{
+    dimensions::Dimensions,
+    menu::{Button as MenuButton, ButtonEvent},
+    vkeyboard::{Button, Key},
+};
+
+const BACK_TXT: &str = "BACK";
+/// In bytes, the server cuts longer messages.
+const CHATSZE: usize = 120;
+const ENTRY_VISIBLE: usize = 24;
+const LINES: usize = 8;
+const LOG_LEN: usize = 50;
+const PROMPT: &str = ">";
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct ChatComponent;
+
+/// Everything said in the lobby and the current room, newest last.
+#[derive(Default, Resource)]
+struct ChatLog {
+    lobby: VecDeque<String>,
+    room: VecDeque<String>,
+}
+
+/// Picks the conversation the chat screen shows, the lobby one if missing.
+#[derive(Resource)]
+pub struct ChatScope {
+    pub room: bool,
+}
+
+#[derive(Resource)]
+struct Entry(String);
+
+#[derive(Component)]
+struct EntryText;
+
+#[derive(Component)]
+struct LogText;
+
+pub struct ChatPlugin;
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChatLog::default())
+            .add_system(receive)
+            .add_system_set(SystemSet::on_enter(AppState::Chat).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Chat).with_system(cleanup))
+            .add_system_set(SystemSet::on_enter(AppState::Lobby).with_system(clear_room))
+            .add_system_set(
+                SystemSet::on_update(AppState::Chat)
+                    .with_system(input)
+                    .with_system(input_keyboard)
+                    .with_system(input_vkeyboard)
+                    .with_system(update_entry)
+                    .with_system(update_log),
+            );
+    }
+}
+
+fn back(app_state: &mut State<AppState>, scope: &ChatScope) {
+    match scope.room {
+        true => app_state.set(AppState::Room).unwrap(),
+        false => app_state.set(AppState::Lobby).unwrap(),
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<ChatComponent>>) {
+    commands.remove_resource::<ChatScope>();
+    commands.remove_resource::<Entry>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The room conversation ends with leaving the room.
+fn clear_room(mut log: ResMut<ChatLog>) {
+    log.room.clear();
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    mut event_reader: EventReader<ButtonEvent>,
+    scope: Res<ChatScope>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            back(&mut app_state, &scope);
+            return;
+        }
+    }
+}
+
+fn input_keyboard(
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut input_char: EventReader<ReceivedCharacter>,
+    input_state: Res<InputState>,
+    scope: Res<ChatScope>,
+) {
+    if !vec![InputState::Keyboard].contains(&input_state) {
+        return;
+    }
+
+    if input.pressed(KeyCode::Back) {
+        input.release(KeyCode::Back);
+        entry.0.pop();
+        return;
+    }
+
+    if input.pressed(KeyCode::Return) {
+        input.release(KeyCode::Return);
+        send(&mut client, &mut entry, &scope);
+        return;
+    }
+
+    for e in input_char.iter() {
+        if !e.char.is_control() && entry.0.len() + e.char.len_utf8() <= CHATSZE {
+            entry.0.push(e.char);
+        }
+    }
+}
+
+fn input_vkeyboard(
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut event_reader: EventReader<Button>,
+    scope: Res<ChatScope>,
+) {
+    for btn in event_reader.iter() {
+        match btn.key {
+            Key::Backspace => {
+                entry.0.pop();
+            }
+            Key::Return => send(&mut client, &mut entry, &scope),
+            _ => {
+                let key = btn.to_string();
+                if entry.0.len() + key.len() <= CHATSZE {
+                    entry.0.push_str(&key);
+                }
+            }
+        }
+    }
+}
+
+fn receive(
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut log: ResMut<ChatLog>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::Chat(msg)) = event {
+            let lines = match *msg.room {
+                true => &mut log.room,
+                false => &mut log.lobby,
+            };
+
+            lines.push_back(format!("{}: {}", *msg.name, *msg.text));
+            if lines.len() > LOG_LEN {
+                lines.pop_front();
+            }
+        }
+    }
+}
+
+fn send(client: &mut Client<Protocol, DefaultChannels>, entry: &mut Entry, scope: &ChatScope) {
+    let text = entry.0.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    client.send_message(
+        DefaultChannels::OrderedReliable,
+        &SendChat::new(scope.room, text.to_string()),
+    );
+    entry.0.clear();
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>, scope: Option<Res<ChatScope>>) {
+    if scope.is_none() {
+        commands.insert_resource(ChatScope { room: false });
+    }
+
+    commands.insert_resource(Entry(String::new()));
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::WHITE,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(LogText)
+        .insert(ChatComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(EntryText)
+        .insert(ChatComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(MenuButton {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: 4,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(ChatComponent);
+}
+
+fn update_entry(
+    dimensions: Res<Dimensions>,
+    entry: Res<Entry>,
+    mut query: Query<(&mut Text, &mut Transform), With<EntryText>>,
+) {
+    // Only the end of a long entry fits on screen
+    let skip = entry.0.chars().count().saturating_sub(ENTRY_VISIBLE);
+    let text = format!(
+        "{} {}_",
+        PROMPT,
+        entry.0.chars().skip(skip).collect::<String>()
+    );
+    for (mut txt, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.clone();
+        }
+
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 3).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_log(
+    dimensions: Res<Dimensions>,
+    log: Res<ChatLog>,
+    mut query: Query<
+        (&mut Text, &mut Text2dBounds, &mut Transform),
+        (With<LogText>, Without<EntryText>),
+    >,
+    scope: Res<ChatScope>,
+) {
+    let lines = match scope.room {
+        true => &log.room,
+        false => &log.lobby,
+    };
+
+    let text = lines
+        .iter()
+        .skip(lines.len().saturating_sub(LINES))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    for (mut txt, mut bounds, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.clone();
+        }
+
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let size = Vec2::new(dimensions.size, 3.0 * dimensions.block);
+        if bounds.size != size {
+            bounds.size = size;
+        }
+
+        let translation = dimensions.translate(0, 0).y + 0.5 * dimensions.block;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
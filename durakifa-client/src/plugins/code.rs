@@ -0,0 +1,338 @@
+use bevy::{
+    input::Input,
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, KeyCode, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, Transform, With,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+use durakifa_protocol::protocol::{JoinRoom, Protocol};
+use naia_bevy_client::{shared::DefaultChannels, Client};
+
+use crate::{AppState, FontAssets, InputState};
+
+use super::{
+    dimensions::Dimensions,
+    menu::{Button as MenuButton, ButtonEvent},
+    vkeyboard::{Button, Key},
+};
+
+const BACK_TXT: &str = "BACK";
+const CODESZE: usize = 6;
+const HINT_TXT: &str = "Leave password empty if none";
+const JOIN_TXT: &str = "JOIN";
+const PASSWORDSZE: usize = 64;
+const PROMPT_CODE: &str = "Invite code:";
+const PROMPT_PASSWORD: &str = "Password:";
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct BtnJoin;
+
+#[derive(Component)]
+struct CodeComponent;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Code,
+    Password,
+}
+
+#[derive(Resource)]
+struct Entry {
+    code: String,
+    field: Field,
+    password: String,
+}
+
+impl Entry {
+    fn pop(&mut self) {
+        match self.field {
+            Field::Code => self.code.pop(),
+            Field::Password => self.password.pop(),
+        };
+    }
+
+    fn push(&mut self, str: &str) {
+        match self.field {
+            Field::Code if self.code.chars().count() < CODESZE => {
+                self.code.push_str(&str.to_uppercase())
+            }
+            Field::Password if self.password.chars().count() < PASSWORDSZE => {
+                self.password.push_str(str)
+            }
+            _ => (),
+        }
+    }
+}
+
+#[derive(Component)]
+struct FieldText(Field);
+
+#[derive(Component)]
+struct Hint;
+
+/// Prefills the code screen, e.g. when joining a locked room from the lobby.
+#[derive(Resource)]
+pub struct InviteCode(pub String);
+
+pub struct CodePlugin;
+impl Plugin for CodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Code).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Code).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Code)
+                    .with_system(input)
+                    .with_system(input_keyboard)
+                    .with_system(input_vkeyboard)
+                    .with_system(update_fields)
+                    .with_system(update_hint),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<CodeComponent>>) {
+    commands.remove_resource::<Entry>();
+    commands.remove_resource::<InviteCode>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    btn_join: Query<&BtnJoin>,
+    mut client: Client<Protocol, DefaultChannels>,
+    entry: Res<Entry>,
+    mut event_reader: EventReader<ButtonEvent>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            app_state.set(AppState::Lobby).unwrap();
+            return;
+        }
+
+        if btn_join.get(event.entity).is_ok() {
+            submit(&mut app_state, &mut client, &entry);
+            return;
+        }
+    }
+}
+
+fn input_keyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut input_char: EventReader<ReceivedCharacter>,
+    input_state: Res<InputState>,
+) {
+    if !vec![InputState::Keyboard].contains(&input_state) {
+        return;
+    }
+
+    if input.pressed(KeyCode::Back) {
+        input.release(KeyCode::Back);
+        entry.pop();
+        return;
+    }
+
+    if input.pressed(KeyCode::Tab) {
+        input.release(KeyCode::Tab);
+        toggle(&mut entry);
+        return;
+    }
+
+    if input.pressed(KeyCode::Return) {
+        input.release(KeyCode::Return);
+        match entry.field {
+            Field::Code => toggle(&mut entry),
+            Field::Password => submit(&mut app_state, &mut client, &entry),
+        }
+
+        return;
+    }
+
+    for e in input_char.iter() {
+        if !e.char.is_control() {
+            entry.push(e.char.to_string().as_str());
+        }
+    }
+}
+
+fn input_vkeyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut event_reader: EventReader<Button>,
+) {
+    for btn in event_reader.iter() {
+        match btn.key {
+            Key::Backspace => entry.pop(),
+            Key::Return => match entry.field {
+                Field::Code => toggle(&mut entry),
+                Field::Password => submit(&mut app_state, &mut client, &entry),
+            },
+            _ => entry.push(btn.to_string().as_str()),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>, invite_code: Option<Res<InviteCode>>) {
+    let code = invite_code.map(|code| code.0.clone()).unwrap_or_default();
+    commands.insert_resource(Entry {
+        field: match code.is_empty() {
+            true => Field::Code,
+            false => Field::Password,
+        },
+        code,
+        password: String::new(),
+    });
+
+    for field in [Field::Code, Field::Password] {
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        color: Color::CYAN,
+                        font: fonts.regular.clone(),
+                        ..default()
+                    },
+                )
+                .with_alignment(TextAlignment::TOP_CENTER),
+                ..default()
+            })
+            .insert(FieldText(field))
+            .insert(CodeComponent);
+    }
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                HINT_TXT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Hint)
+        .insert(CodeComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnJoin)
+        .insert(MenuButton {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: 3,
+            text: JOIN_TXT.to_string(),
+        })
+        .insert(CodeComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(MenuButton {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: 4,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(CodeComponent);
+}
+
+fn submit(
+    app_state: &mut State<AppState>,
+    client: &mut Client<Protocol, DefaultChannels>,
+    entry: &Entry,
+) {
+    let code = entry.code.trim();
+    if code.is_empty() {
+        return;
+    }
+
+    let password = match entry.password.is_empty() {
+        true => None,
+        false => Some(entry.password.clone()),
+    };
+
+    client.send_message(
+        DefaultChannels::UnorderedReliable,
+        &JoinRoom::by_code(code.to_string(), password),
+    );
+    app_state.set(AppState::Room).unwrap();
+}
+
+fn toggle(entry: &mut Entry) {
+    entry.field = match entry.field {
+        Field::Code => Field::Password,
+        Field::Password => Field::Code,
+    };
+}
+
+fn update_fields(
+    dimensions: Res<Dimensions>,
+    entry: Res<Entry>,
+    mut query: Query<(&FieldText, &mut Text, &mut Transform)>,
+) {
+    for (field, mut txt, mut tf) in query.iter_mut() {
+        let cursor = match entry.field == field.0 {
+            true => "_",
+            false => "",
+        };
+
+        let (text, position) = match field.0 {
+            Field::Code => (format!("{} {}{}", PROMPT_CODE, entry.code, cursor), 0),
+            Field::Password => (
+                format!(
+                    "{} {}{}",
+                    PROMPT_PASSWORD,
+                    "*".repeat(entry.password.chars().count()),
+                    cursor
+                ),
+                1,
+            ),
+        };
+
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text;
+        }
+
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, position).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_hint(
+    dimensions: Res<Dimensions>,
+    mut query: Query<(&mut Text, &mut Transform), With<Hint>>,
+) {
+    for (mut txt, mut tf) in query.iter_mut() {
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 2).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
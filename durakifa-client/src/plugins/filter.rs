@@ -0,0 +1,308 @@
+use bevy::{
+    input::Input,
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, KeyCode, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, Transform, With, Without,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+use durakifa_protocol::protocol::{Name, Room, RoomStatus, RoomSummary, Variant};
+
+use crate::{AppState, FontAssets, InputState};
+
+use super::{
+    dimensions::Dimensions,
+    lobby::variant_text,
+    menu::{Button as MenuButton, ButtonEvent},
+    vkeyboard::{Button, Key},
+};
+
+const ALL_TXT: &str = "ROOM555: ALL";
+const BACK_TXT: &str = "BACK";
+const HINT_TXT: &str = "Room or owner name, or invite code";
+const OPEN_TXT: &str = "ROOM555: OPÉN";
+const PROMPT_SEARCH: &str = "Search:";
+const SEARCHSZE: usize = 30;
+const VARIANT_TXT: &str = "VARIANT:";
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct BtnOpen;
+
+#[derive(Component)]
+struct BtnVariant;
+
+#[derive(Component)]
+struct FilterComponent;
+
+#[derive(Component)]
+struct Hint;
+
+/// Narrows down the rooms listed in the lobby, kept while the client runs.
+#[derive(Default, Resource)]
+pub struct RoomFilter {
+    /// Only rooms waiting for players with a free seat.
+    pub open: bool,
+    pub search: String,
+    pub variant: Option<Variant>,
+}
+
+impl RoomFilter {
+    pub fn matches(&self, name: &Name, room: &Room, summary: &RoomSummary) -> bool {
+        if self.open
+            && (*room.status != RoomStatus::Waiting || *summary.players >= *summary.max_players)
+        {
+            return false;
+        }
+
+        if let Some(variant) = &self.variant {
+            if *summary.variant != *variant {
+                return false;
+            }
+        }
+
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || name.name.to_lowercase().contains(&search)
+            || room.code.to_lowercase() == search
+    }
+}
+
+#[derive(Component)]
+struct SearchText;
+
+pub struct FilterPlugin;
+impl Plugin for FilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RoomFilter::default())
+            .add_system_set(SystemSet::on_enter(AppState::Filter).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Filter).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Filter)
+                    .with_system(input)
+                    .with_system(input_keyboard)
+                    .with_system(input_vkeyboard)
+                    .with_system(update_buttons)
+                    .with_system(update_search),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<FilterComponent>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    btn_open: Query<&BtnOpen>,
+    btn_variant: Query<&BtnVariant>,
+    mut event_reader: EventReader<ButtonEvent>,
+    mut filter: ResMut<RoomFilter>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            app_state.set(AppState::Lobby).unwrap();
+            return;
+        }
+
+        if btn_open.get(event.entity).is_ok() {
+            filter.open = !filter.open;
+            return;
+        }
+
+        if btn_variant.get(event.entity).is_ok() {
+            filter.variant = match filter.variant {
+                None => Some(Variant::Podkidnoy),
+                Some(Variant::Podkidnoy) => Some(Variant::Perevodnoy),
+                Some(Variant::Perevodnoy) => None,
+            };
+            return;
+        }
+    }
+}
+
+fn input_keyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut filter: ResMut<RoomFilter>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut input_char: EventReader<ReceivedCharacter>,
+    input_state: Res<InputState>,
+) {
+    if !vec![InputState::Keyboard].contains(&input_state) {
+        return;
+    }
+
+    if input.pressed(KeyCode::Back) {
+        input.release(KeyCode::Back);
+        filter.search.pop();
+        return;
+    }
+
+    if input.pressed(KeyCode::Return) {
+        input.release(KeyCode::Return);
+        app_state.set(AppState::Lobby).unwrap();
+        return;
+    }
+
+    for e in input_char.iter() {
+        if !e.char.is_control() && filter.search.chars().count() < SEARCHSZE {
+            filter.search.push(e.char);
+        }
+    }
+}
+
+fn input_vkeyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut event_reader: EventReader<Button>,
+    mut filter: ResMut<RoomFilter>,
+) {
+    for btn in event_reader.iter() {
+        match btn.key {
+            Key::Backspace => {
+                filter.search.pop();
+            }
+            Key::Return => app_state.set(AppState::Lobby).unwrap(),
+            _ if filter.search.chars().count() < SEARCHSZE => {
+                filter.search.push_str(btn.to_string().as_str())
+            }
+            _ => (),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(SearchText)
+        .insert(FilterComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                HINT_TXT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Hint)
+        .insert(FilterComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnOpen)
+        .insert(MenuButton {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 2,
+            text: String::new(),
+        })
+        .insert(FilterComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnVariant)
+        .insert(MenuButton {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 3,
+            text: String::new(),
+        })
+        .insert(FilterComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(MenuButton {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: 4,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(FilterComponent);
+}
+
+fn update_buttons(
+    filter: Res<RoomFilter>,
+    mut open: Query<&mut MenuButton, With<BtnOpen>>,
+    mut variant: Query<&mut MenuButton, (With<BtnVariant>, Without<BtnOpen>)>,
+) {
+    let text = match filter.open {
+        true => OPEN_TXT,
+        false => ALL_TXT,
+    };
+
+    for mut btn in open.iter_mut() {
+        if btn.text != text {
+            btn.text = text.to_string();
+        }
+    }
+
+    let text = match &filter.variant {
+        Some(variant) => format!("{} {}", VARIANT_TXT, variant_text(variant)),
+        None => format!("{} ANY", VARIANT_TXT),
+    };
+
+    for mut btn in variant.iter_mut() {
+        if btn.text != text {
+            btn.text = text.clone();
+        }
+    }
+}
+
+fn update_search(
+    dimensions: Res<Dimensions>,
+    filter: Res<RoomFilter>,
+    mut hint: Query<(&mut Text, &mut Transform), With<Hint>>,
+    mut search: Query<(&mut Text, &mut Transform), (With<SearchText>, Without<Hint>)>,
+) {
+    for (mut txt, mut tf) in search.iter_mut() {
+        let text = format!("{} {}_", PROMPT_SEARCH, filter.search);
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text;
+        }
+
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 0).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+
+    for (mut txt, mut tf) in hint.iter_mut() {
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 1).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::{
+    App, Color, Commands, Component, Entity, EventReader, Plugin, Query, ResMut, State, SystemSet,
+    With,
+};
+use durakifa_protocol::protocol::{Protocol, RequestLeaderboard};
+use naia_bevy_client::{events::MessageEvent, shared::DefaultChannels, Client};
+
+use crate::AppState;
+
+use super::{
+    dimensions::GRID_SZE,
+    menu::{Button, ButtonEvent},
+};
+
+const BACK_TXT: &str = "BACK";
+const GUEST_TXT: &str = "Guests are not rated";
+const ROWS: usize = GRID_SZE - 2;
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct LeaderboardComponent;
+
+pub struct LeaderboardPlugin;
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Leaderboard).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Leaderboard).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Leaderboard)
+                    .with_system(input)
+                    .with_system(update_rows),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<LeaderboardComponent>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    mut event_reader: EventReader<ButtonEvent>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            app_state.set(AppState::Lobby).unwrap();
+            return;
+        }
+    }
+}
+
+fn setup(mut client: Client<Protocol, DefaultChannels>, mut commands: Commands) {
+    client.send_message(
+        DefaultChannels::UnorderedReliable,
+        &RequestLeaderboard::new(ROWS as u8),
+    );
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(Button {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 1,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(LeaderboardComponent);
+}
+
+fn update_rows(
+    mut commands: Commands,
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::Leaderboard(msg)) = event {
+            let first = *msg.first as usize;
+            for (i, (name, rating)) in msg.names.iter().zip(msg.ratings.iter()).enumerate() {
+                let position = first + i;
+                if position >= ROWS {
+                    break;
+                }
+
+                commands
+                    .spawn_empty()
+                    .insert(Button {
+                        color_bg: Color::MIDNIGHT_BLUE,
+                        color_fg: Color::YELLOW,
+                        position,
+                        text: format!("{}. {} {}", position + 1, name, rating),
+                    })
+                    .insert(LeaderboardComponent);
+            }
+
+            // Every page carries the own rank, the first one shows it
+            if first > 0 {
+                continue;
+            }
+
+            let own = match (*msg.rank, *msg.rating) {
+                (Some(rank), Some(rating)) => format!("You: {}. {}", rank, rating),
+                _ => GUEST_TXT.to_string(),
+            };
+
+            commands
+                .spawn_empty()
+                .insert(Button {
+                    color_bg: Color::DARK_GRAY,
+                    color_fg: Color::PINK,
+                    position: ROWS,
+                    text: own,
+                })
+                .insert(LeaderboardComponent);
+        }
+    }
+}
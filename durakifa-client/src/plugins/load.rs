@@ -27,8 +27,10 @@ const ROTATION_SPEED: f32 = PI * 0.2; // Angle per Second
 pub struct LoadPlugin;
 impl Plugin for LoadPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Load).with_system(setup))
+        app.add_system_set(SystemSet::on_enter(AppState::Connect).with_system(setup))
+            .add_system_set(SystemSet::on_enter(AppState::Load).with_system(setup))
             .add_system_set(SystemSet::on_exit(AppState::Connect).with_system(cleanup))
+            .add_system_set(SystemSet::on_exit(AppState::Load).with_system(cleanup))
             .add_system_set(SystemSet::on_update(AppState::Connect).with_system(connect))
             .add_system_set(SystemSet::on_update(AppState::Connect).with_system(update_rotation))
             .add_system_set(
@@ -71,7 +73,7 @@ fn cleanup(mut commands: Commands, query: Query<Entity, With<LoadComponent>>) {
 
 fn connect(mut app_state: ResMut<State<AppState>>, net_state: Res<State<NetState>>) {
     if net_state.is_changed() && vec![NetState::Online].contains(net_state.current()) {
-        app_state.set(AppState::Register).unwrap();
+        app_state.set(AppState::Lobby).unwrap();
     }
 }
 
@@ -1,25 +1,73 @@
-use bevy::prelude::{
-    App, Changed, Color, Commands, Component, Entity, EventReader, Local, Plugin, Query, ResMut,
-    State, SystemSet, With, Without,
+use bevy::{
+    input::{mouse::MouseWheel, Input},
+    prelude::{
+        App, Changed, Color, Commands, Component, Entity, EventReader, GamepadButton,
+        GamepadButtonType, Gamepads, KeyCode, Local, Or, Plugin, Query, Res, ResMut, Resource,
+        State, SystemSet, With, Without,
+    },
+};
+use durakifa_protocol::protocol::{
+    JoinRoom, Name, Online, Protocol, Room, RoomStatus, RoomSummary, Variant, WatchRoom,
 };
-use durakifa_protocol::protocol::{CreateRoom, JoinRoom, Name, Protocol, Room};
 use naia_bevy_client::{shared::DefaultChannels, Client};
 
 use crate::AppState;
 
 use super::{
+    chat::ChatScope,
+    code::InviteCode,
     dimensions::GRID_SZE,
+    filter::RoomFilter,
     menu::{Button, ButtonEvent},
 };
 
+const CHAT_TXT: &str = "CHAT";
+const CODE_TXT: &str = "JOIN BY CODÉ";
+const FILTER_TXT: &str = "FILTÉR";
+const FINISHED_TXT: &str = "OVÉR";
+const INGAME_TXT: &str = "PLAYING";
+const LEADERBOARD_TXT: &str = "LÉADERBOARD";
+const LOCKED_TXT: &str = "LOCKÉD";
 const NEWROOM_TXT: &str = "NÉW ROOM";
+const ONLINE_TXT: &str = "ONLINÉ";
+const PLAY_TXT: &str = "MODÉ: PLAY";
+const QUICKPLAY_TXT: &str = "QUICK PLAY";
+/// Rows left for the room list above the buttons.
+const ROOM_ROWS: usize = GRID_SZE - 7;
+const WATCH_TXT: &str = "MODÉ: WATCH";
+
+#[derive(Component)]
+struct BtnChat;
+
+#[derive(Component)]
+struct BtnCode;
+
+#[derive(Component)]
+struct BtnFilter;
+
+#[derive(Component)]
+struct BtnLeaderboard;
 
 #[derive(Component)]
 struct BtnNewRoom;
 
+#[derive(Component)]
+struct BtnQuickPlay;
+
+#[derive(Component)]
+struct BtnWatch;
+
 #[derive(Component)]
 struct LobbyComponent;
 
+/// Index of the first room on screen among those that pass the filter.
+#[derive(Resource)]
+struct Scroll(usize);
+
+/// Picking a room watches it instead of taking a seat.
+#[derive(Resource)]
+struct WatchMode(bool);
+
 pub struct LobbyPlugin;
 impl Plugin for LobbyPlugin {
     fn build(&self, app: &mut App) {
@@ -28,13 +76,17 @@ impl Plugin for LobbyPlugin {
             .add_system_set(
                 SystemSet::on_update(AppState::Lobby)
                     .with_system(input)
-                    .with_system(update_room_names)
+                    .with_system(input_scroll)
+                    .with_system(update_online)
+                    .with_system(update_room_rows)
                     .with_system(update_rooms),
             );
     }
 }
 
 fn cleanup(mut commands: Commands, query: Query<Entity, With<LobbyComponent>>) {
+    commands.remove_resource::<Scroll>();
+    commands.remove_resource::<WatchMode>();
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
@@ -42,21 +94,79 @@ fn cleanup(mut commands: Commands, query: Query<Entity, With<LobbyComponent>>) {
 
 fn input(
     mut app_state: ResMut<State<AppState>>,
+    btn_chat: Query<&BtnChat>,
+    btn_code: Query<&BtnCode>,
+    btn_filter: Query<&BtnFilter>,
+    btn_leaderboard: Query<&BtnLeaderboard>,
     btn_new: Query<&BtnNewRoom>,
-    btn_room: Query<&Button>,
+    btn_quick: Query<&BtnQuickPlay>,
+    btn_room: Query<&Room, With<Button>>,
+    mut btn_watch: Query<&mut Button, With<BtnWatch>>,
     mut client: Client<Protocol, DefaultChannels>,
+    mut commands: Commands,
     mut event_reader: EventReader<ButtonEvent>,
+    mut watch_mode: ResMut<WatchMode>,
 ) {
     for event in event_reader.iter() {
+        if btn_chat.get(event.entity).is_ok() {
+            commands.insert_resource(ChatScope { room: false });
+            app_state.set(AppState::Chat).unwrap();
+            return;
+        }
+
+        if btn_code.get(event.entity).is_ok() {
+            app_state.set(AppState::Code).unwrap();
+            return;
+        }
+
+        if btn_filter.get(event.entity).is_ok() {
+            app_state.set(AppState::Filter).unwrap();
+            return;
+        }
+
+        if btn_leaderboard.get(event.entity).is_ok() {
+            app_state.set(AppState::Leaderboard).unwrap();
+            return;
+        }
+
         if btn_new.get(event.entity).is_ok() {
-            app_state.set(AppState::Room).unwrap();
-            client.send_message(DefaultChannels::UnorderedReliable, &CreateRoom::new());
+            app_state.set(AppState::NewRoom).unwrap();
+            return;
+        }
+
+        if btn_quick.get(event.entity).is_ok() {
+            app_state.set(AppState::QuickPlay).unwrap();
+            return;
+        }
+
+        if let Ok(mut btn) = btn_watch.get_mut(event.entity) {
+            watch_mode.0 = !watch_mode.0;
+            btn.text = match watch_mode.0 {
+                true => WATCH_TXT,
+                false => PLAY_TXT,
+            }
+            .to_string();
             return;
         }
 
-        if btn_room.get(event.entity).is_ok() {
+        if let Ok(room) = btn_room.get(event.entity) {
+            if watch_mode.0 {
+                app_state.set(AppState::Room).unwrap();
+                let mut watch = WatchRoom::new(None);
+                watch.room.set(&client, &event.entity);
+                client.send_message(DefaultChannels::UnorderedReliable, &watch);
+                return;
+            }
+
+            // Locked rooms ask for the password on the code screen
+            if *room.locked {
+                commands.insert_resource(InviteCode((*room.code).clone()));
+                app_state.set(AppState::Code).unwrap();
+                return;
+            }
+
             app_state.set(AppState::Room).unwrap();
-            let mut join = JoinRoom::new();
+            let mut join = JoinRoom::new(None);
             join.room.set(&client, &event.entity);
             client.send_message(DefaultChannels::UnorderedReliable, &join);
             return;
@@ -64,7 +174,162 @@ fn input(
     }
 }
 
+/// Scrolls the room list with the mouse wheel, the arrow and page keys or the d-pad.
+/// `update_rooms` keeps the scroll position within the list.
+fn input_scroll(
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut scroll: ResMut<Scroll>,
+) {
+    let mut delta = 0;
+    for event in mouse_wheel.iter() {
+        if event.y > 0.0 {
+            delta -= 1;
+        } else if event.y < 0.0 {
+            delta += 1;
+        }
+    }
+
+    for (key, rows) in [
+        (KeyCode::Up, -1),
+        (KeyCode::Down, 1),
+        (KeyCode::PageUp, -(ROOM_ROWS as i32)),
+        (KeyCode::PageDown, ROOM_ROWS as i32),
+    ] {
+        if input.pressed(key) {
+            input.release(key);
+            delta += rows;
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        for (button_type, rows) in [
+            (GamepadButtonType::DPadUp, -1),
+            (GamepadButtonType::DPadDown, 1),
+            (GamepadButtonType::LeftTrigger, -(ROOM_ROWS as i32)),
+            (GamepadButtonType::RightTrigger, ROOM_ROWS as i32),
+        ] {
+            if gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)) {
+                delta += rows;
+            }
+        }
+    }
+
+    if delta != 0 {
+        scroll.0 = (scroll.0 as i32 + delta).max(0) as usize;
+    }
+}
+
+/// Rooms that cannot be joined are greyed out.
+fn row_color(room: &Room, summary: &RoomSummary) -> Color {
+    match *room.status == RoomStatus::Waiting && *summary.players < *summary.max_players {
+        true => Color::MIDNIGHT_BLUE,
+        false => Color::DARK_GRAY,
+    }
+}
+
+/// Label of a room row, e.g. "Alice 2/6 PODKIDNOY LOCKÉD PLAYING".
+fn row_text(name: &Name, room: &Room, summary: &RoomSummary) -> String {
+    let mut text = format!(
+        "{} {}/{} {}",
+        *name.name,
+        *summary.players,
+        *summary.max_players,
+        variant_text(&summary.variant)
+    );
+
+    if *room.locked {
+        text.push(' ');
+        text.push_str(LOCKED_TXT);
+    }
+
+    match *room.status {
+        RoomStatus::Finished => {
+            text.push(' ');
+            text.push_str(FINISHED_TXT);
+        }
+        RoomStatus::InGame => {
+            text.push(' ');
+            text.push_str(INGAME_TXT);
+        }
+        RoomStatus::Waiting => (),
+    }
+
+    text
+}
+
 fn setup(mut commands: Commands) {
+    commands.insert_resource(Scroll(0));
+    commands.insert_resource(WatchMode(false));
+
+    commands
+        .spawn_empty()
+        .insert(BtnFilter)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 7,
+            text: FILTER_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnQuickPlay)
+        .insert(Button {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 6,
+            text: QUICKPLAY_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnChat)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 5,
+            text: CHAT_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnWatch)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 4,
+            text: PLAY_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnCode)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 3,
+            text: CODE_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnLeaderboard)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 2,
+            text: LEADERBOARD_TXT.to_string(),
+        })
+        .insert(LobbyComponent);
+
     commands
         .spawn_empty()
         .insert(BtnNewRoom)
@@ -77,34 +342,121 @@ fn setup(mut commands: Commands) {
         .insert(LobbyComponent);
 }
 
-fn update_room_names(mut query: Query<(&mut Button, &Name), Changed<Name>>) {
-    for (mut btn, name) in query.iter_mut() {
-        btn.text = (*name.name).clone();
+/// The lobby chat reaches everyone online, so its button tells how many that are.
+fn update_online(mut btn_chat: Query<&mut Button, With<BtnChat>>, online: Query<&Online>) {
+    let text = match online.iter().next() {
+        Some(online) => format!("{} ({} {})", CHAT_TXT, *online.users, ONLINE_TXT),
+        None => CHAT_TXT.to_string(),
+    };
+
+    for mut btn in btn_chat.iter_mut() {
+        if btn.text != text {
+            btn.text = text.clone();
+        }
+    }
+}
+
+fn update_room_rows(
+    mut query: Query<
+        (&mut Button, &Name, &Room, &RoomSummary),
+        Or<(Changed<Name>, Changed<Room>, Changed<RoomSummary>)>,
+    >,
+) {
+    for (mut btn, name, room, summary) in query.iter_mut() {
+        let text = row_text(name, room, summary);
+        if btn.text != text {
+            btn.text = text;
+        }
+
+        let color_bg = row_color(room, summary);
+        if btn.color_bg != color_bg {
+            btn.color_bg = color_bg;
+        }
     }
 }
 
+/// Lays out the rooms that pass the filter from the scroll position on, in the order they
+/// showed up. Rooms off screen lose their button.
 fn update_rooms(
-    mut buttons: Query<&mut Button>,
+    mut btn_filter: Query<&mut Button, (With<BtnFilter>, Without<Room>)>,
     mut commands: Commands,
-    query: Query<(Entity, &Name), (With<Room>, Without<Button>)>,
+    filter: Res<RoomFilter>,
+    mut query: Query<(Entity, &Name, &Room, &RoomSummary, Option<&mut Button>)>,
     mut rooms: Local<Vec<Entity>>,
+    mut scroll: ResMut<Scroll>,
 ) {
-    let len = rooms.len();
-    rooms.retain(|entity| buttons.contains(*entity));
-    if rooms.len() != len {
-        for (i, entity) in rooms.iter().enumerate() {
-            buttons.get_mut(*entity).unwrap().position = i;
+    rooms.retain(|entity| query.contains(*entity));
+    for (entity, _, _, _, _) in query.iter() {
+        if !rooms.contains(&entity) {
+            rooms.push(entity);
+        }
+    }
+
+    let matching = rooms
+        .iter()
+        .filter(|entity| match query.get(**entity) {
+            Ok((_, name, room, summary, _)) => filter.matches(name, room, summary),
+            Err(_) => false,
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    let max_scroll = matching.len().saturating_sub(ROOM_ROWS);
+    if scroll.0 > max_scroll {
+        scroll.0 = max_scroll;
+    }
+
+    for entity in rooms.iter() {
+        let position = matching
+            .iter()
+            .position(|other| other == entity)
+            .and_then(|index| index.checked_sub(scroll.0))
+            .filter(|position| *position < ROOM_ROWS);
+
+        if let Ok((_, name, room, summary, btn)) = query.get_mut(*entity) {
+            match (position, btn) {
+                (Some(position), Some(mut btn)) => {
+                    if btn.position != position {
+                        btn.position = position;
+                    }
+                }
+                (Some(position), None) => {
+                    commands.entity(*entity).insert(Button {
+                        color_fg: Color::YELLOW,
+                        color_bg: row_color(room, summary),
+                        position,
+                        text: row_text(name, room, summary),
+                    });
+                }
+                (None, Some(_)) => {
+                    commands.entity(*entity).remove::<Button>();
+                }
+                (None, None) => (),
+            }
         }
     }
 
-    for (entity, name) in query.iter() {
-        commands.entity(entity).insert(Button {
-            color_fg: Color::YELLOW,
-            color_bg: Color::MIDNIGHT_BLUE,
-            position: rooms.len(),
-            text: (*name.name).clone(),
-        });
+    let text = match matching.len() {
+        0 => format!("{}: NO ROOM555", FILTER_TXT),
+        len => format!(
+            "{}: {}-{} OF {}",
+            FILTER_TXT,
+            scroll.0 + 1,
+            (scroll.0 + ROOM_ROWS).min(len),
+            len
+        ),
+    };
+
+    for mut btn in btn_filter.iter_mut() {
+        if btn.text != text {
+            btn.text = text.clone();
+        }
+    }
+}
 
-        rooms.push(entity);
+pub fn variant_text(variant: &Variant) -> &'static str {
+    match variant {
+        Variant::Perevodnoy => "PÉRÉVODNOY",
+        Variant::Podkidnoy => "PODKIDNOY",
     }
 }
@@ -53,8 +53,17 @@ struct MenuComponent;
 pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
+        add_menu_to_state(app, AppState::Chat);
+        add_menu_to_state(app, AppState::Code);
+        add_menu_to_state(app, AppState::Filter);
+        add_menu_to_state(app, AppState::Leaderboard);
         add_menu_to_state(app, AppState::Lobby);
+        add_menu_to_state(app, AppState::NewRoom);
+        add_menu_to_state(app, AppState::QuickPlay);
+        add_menu_to_state(app, AppState::Reconnect);
+        add_menu_to_state(app, AppState::Register);
         add_menu_to_state(app, AppState::Room);
+        add_menu_to_state(app, AppState::Server);
         app.add_event::<ButtonEvent>();
     }
 }
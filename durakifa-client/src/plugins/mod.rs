@@ -1,8 +1,17 @@
+pub mod chat;
+pub mod code;
 pub mod dimensions;
+pub mod filter;
+pub mod leaderboard;
 pub mod load;
 pub mod lobby;
 pub mod menu;
 pub mod mouse;
+pub mod new_room;
+pub mod quick_play;
+pub mod reconnect;
 pub mod register;
 pub mod room;
+pub mod server;
+pub mod toast;
 pub mod vkeyboard;
@@ -0,0 +1,336 @@
+use bevy::{
+    input::Input,
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, KeyCode, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, Transform, With, Without,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+use durakifa_protocol::protocol::{CreateRoom, Protocol, Variant};
+use naia_bevy_client::{shared::DefaultChannels, Client};
+
+use crate::{AppState, FontAssets, InputState};
+
+use super::{
+    dimensions::Dimensions,
+    lobby::variant_text,
+    menu::{Button as MenuButton, ButtonEvent},
+    vkeyboard::{Button, Key},
+};
+
+const BACK_TXT: &str = "BACK";
+const CREATE_TXT: &str = "CRÉATE";
+const HINT_TXT: &str = "No password: anyone may join";
+const PASSWORDSZE: usize = 64;
+const PRIVATE_TXT: &str = "PRIVATÉ";
+const PROMPT_PASSWORD: &str = "Room password:";
+const PUBLIC_TXT: &str = "PUBLIC";
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct BtnCreate;
+
+#[derive(Component)]
+struct BtnPrivate;
+
+#[derive(Component)]
+struct BtnVariant;
+
+#[derive(Resource)]
+struct Entry {
+    password: String,
+    private: bool,
+    variant: Variant,
+}
+
+#[derive(Component)]
+struct Hint;
+
+#[derive(Component)]
+struct NewRoomComponent;
+
+#[derive(Component)]
+struct PasswordText;
+
+pub struct NewRoomPlugin;
+impl Plugin for NewRoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::NewRoom).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::NewRoom).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::NewRoom)
+                    .with_system(input)
+                    .with_system(input_keyboard)
+                    .with_system(input_vkeyboard)
+                    .with_system(update_password)
+                    .with_system(update_private)
+                    .with_system(update_variant),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<NewRoomComponent>>) {
+    commands.remove_resource::<Entry>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    btn_create: Query<&BtnCreate>,
+    btn_private: Query<&BtnPrivate>,
+    btn_variant: Query<&BtnVariant>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut event_reader: EventReader<ButtonEvent>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            app_state.set(AppState::Lobby).unwrap();
+            return;
+        }
+
+        if btn_create.get(event.entity).is_ok() {
+            submit(&mut app_state, &mut client, &entry);
+            return;
+        }
+
+        if btn_private.get(event.entity).is_ok() {
+            entry.private = !entry.private;
+            return;
+        }
+
+        if btn_variant.get(event.entity).is_ok() {
+            entry.variant = match entry.variant {
+                Variant::Perevodnoy => Variant::Podkidnoy,
+                Variant::Podkidnoy => Variant::Perevodnoy,
+            };
+            return;
+        }
+    }
+}
+
+fn input_keyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut input_char: EventReader<ReceivedCharacter>,
+    input_state: Res<InputState>,
+) {
+    if !vec![InputState::Keyboard].contains(&input_state) {
+        return;
+    }
+
+    if input.pressed(KeyCode::Back) {
+        input.release(KeyCode::Back);
+        entry.password.pop();
+        return;
+    }
+
+    if input.pressed(KeyCode::Tab) {
+        input.release(KeyCode::Tab);
+        entry.private = !entry.private;
+        return;
+    }
+
+    if input.pressed(KeyCode::Return) {
+        input.release(KeyCode::Return);
+        submit(&mut app_state, &mut client, &entry);
+        return;
+    }
+
+    for e in input_char.iter() {
+        if !e.char.is_control() && entry.password.chars().count() < PASSWORDSZE {
+            entry.password.push(e.char);
+        }
+    }
+}
+
+fn input_vkeyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
+    mut event_reader: EventReader<Button>,
+) {
+    for btn in event_reader.iter() {
+        match btn.key {
+            Key::Backspace => {
+                entry.password.pop();
+            }
+            Key::Return => submit(&mut app_state, &mut client, &entry),
+            _ if entry.password.chars().count() < PASSWORDSZE => {
+                entry.password.push_str(btn.to_string().as_str())
+            }
+            _ => (),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+    commands.insert_resource(Entry {
+        password: String::new(),
+        private: false,
+        variant: Variant::Podkidnoy,
+    });
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(PasswordText)
+        .insert(NewRoomComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                HINT_TXT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Hint)
+        .insert(NewRoomComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnPrivate)
+        .insert(MenuButton {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 2,
+            text: PUBLIC_TXT.to_string(),
+        })
+        .insert(NewRoomComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnVariant)
+        .insert(MenuButton {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 3,
+            text: variant_text(&Variant::Podkidnoy).to_string(),
+        })
+        .insert(NewRoomComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnCreate)
+        .insert(MenuButton {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: 4,
+            text: CREATE_TXT.to_string(),
+        })
+        .insert(NewRoomComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(MenuButton {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: 5,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(NewRoomComponent);
+}
+
+fn submit(
+    app_state: &mut State<AppState>,
+    client: &mut Client<Protocol, DefaultChannels>,
+    entry: &Entry,
+) {
+    let password = match entry.password.is_empty() {
+        true => None,
+        false => Some(entry.password.clone()),
+    };
+
+    client.send_message(
+        DefaultChannels::UnorderedReliable,
+        &CreateRoom::new(password, entry.private, entry.variant.clone()),
+    );
+    app_state.set(AppState::Room).unwrap();
+}
+
+fn update_password(
+    dimensions: Res<Dimensions>,
+    entry: Res<Entry>,
+    mut hint: Query<(&mut Text, &mut Transform), With<Hint>>,
+    mut password: Query<(&mut Text, &mut Transform), (With<PasswordText>, Without<Hint>)>,
+) {
+    for (mut txt, mut tf) in password.iter_mut() {
+        let text = format!(
+            "{} {}_",
+            PROMPT_PASSWORD,
+            "*".repeat(entry.password.chars().count())
+        );
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text;
+        }
+
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 0).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+
+    for (mut txt, mut tf) in hint.iter_mut() {
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 1).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_private(entry: Res<Entry>, mut query: Query<&mut MenuButton, With<BtnPrivate>>) {
+    let text = match entry.private {
+        true => PRIVATE_TXT,
+        false => PUBLIC_TXT,
+    };
+
+    for mut btn in query.iter_mut() {
+        if btn.text != text {
+            btn.text = text.to_string();
+        }
+    }
+}
+
+fn update_variant(entry: Res<Entry>, mut query: Query<&mut MenuButton, With<BtnVariant>>) {
+    let text = variant_text(&entry.variant);
+    for mut btn in query.iter_mut() {
+        if btn.text != text {
+            btn.text = text.to_string();
+        }
+    }
+}
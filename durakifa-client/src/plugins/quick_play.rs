@@ -0,0 +1,277 @@
+use bevy::{
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, Plugin, Query, Res, ResMut,
+        Resource, State, SystemSet, Transform, With, Without,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+};
+use durakifa_protocol::protocol::{LeaveRoom, Player, Protocol, QuickPlay, Variant};
+use naia_bevy_client::{shared::DefaultChannels, Client};
+
+use crate::{AppState, FontAssets, LocalUser};
+
+use super::{
+    dimensions::Dimensions,
+    lobby::variant_text,
+    menu::{Button, ButtonEvent},
+    room::own_room,
+};
+
+const BACK_TXT: &str = "BACK";
+const CANCEL_TXT: &str = "CANCÉL";
+const HINT_TXT: &str = "Bots fill in if nobody shows up";
+const PLAYERS_MAX: u8 = 6;
+const PLAYERS_MIN: u8 = 2;
+const PLAYERS_TXT: &str = "PLAYÉR555:";
+const SEARCH_TXT: &str = "555ÉARCH";
+const SEARCHING_TXT: &str = "Waiting for players...";
+const VARIANT_TXT: &str = "VARIANT:";
+
+#[derive(Component)]
+struct BtnBack;
+
+#[derive(Component)]
+struct BtnPlayers;
+
+#[derive(Component)]
+struct BtnSearch;
+
+#[derive(Component)]
+struct BtnVariant;
+
+#[derive(Component)]
+struct Hint;
+
+/// Settings of the quick match, `None` accepts any.
+#[derive(Resource)]
+struct Preferences {
+    players: Option<u8>,
+    searching: bool,
+    variant: Option<Variant>,
+}
+
+#[derive(Component)]
+struct QuickPlayComponent;
+
+pub struct QuickPlayPlugin;
+impl Plugin for QuickPlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::QuickPlay).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::QuickPlay).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::QuickPlay)
+                    .with_system(input)
+                    .with_system(update_buttons)
+                    .with_system(update_hint)
+                    .with_system(update_match),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<QuickPlayComponent>>) {
+    commands.remove_resource::<Preferences>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_back: Query<&BtnBack>,
+    btn_players: Query<&BtnPlayers>,
+    btn_search: Query<&BtnSearch>,
+    btn_variant: Query<&BtnVariant>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut event_reader: EventReader<ButtonEvent>,
+    mut preferences: ResMut<Preferences>,
+) {
+    for event in event_reader.iter() {
+        if btn_back.get(event.entity).is_ok() {
+            if preferences.searching {
+                client.send_message(DefaultChannels::UnorderedReliable, &LeaveRoom::new());
+            }
+
+            app_state.set(AppState::Lobby).unwrap();
+            return;
+        }
+
+        if btn_players.get(event.entity).is_ok() {
+            preferences.players = match preferences.players {
+                None => Some(PLAYERS_MIN),
+                Some(players) if players < PLAYERS_MAX => Some(players + 1),
+                Some(_) => None,
+            };
+        }
+
+        if btn_search.get(event.entity).is_ok() {
+            preferences.searching = !preferences.searching;
+            if !preferences.searching {
+                client.send_message(DefaultChannels::UnorderedReliable, &LeaveRoom::new());
+                return;
+            }
+        }
+
+        if btn_variant.get(event.entity).is_ok() {
+            preferences.variant = match preferences.variant {
+                None => Some(Variant::Podkidnoy),
+                Some(Variant::Podkidnoy) => Some(Variant::Perevodnoy),
+                Some(Variant::Perevodnoy) => None,
+            };
+        }
+
+        // The server keeps the place in the queue when the settings change
+        if preferences.searching {
+            client.send_message(
+                DefaultChannels::UnorderedReliable,
+                &QuickPlay::new(preferences.players, preferences.variant.clone()),
+            );
+        }
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+    commands.insert_resource(Preferences {
+        players: None,
+        searching: false,
+        variant: None,
+    });
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                HINT_TXT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Hint)
+        .insert(QuickPlayComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnVariant)
+        .insert(Button {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 1,
+            text: String::new(),
+        })
+        .insert(QuickPlayComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnPlayers)
+        .insert(Button {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 2,
+            text: String::new(),
+        })
+        .insert(QuickPlayComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnSearch)
+        .insert(Button {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: 3,
+            text: SEARCH_TXT.to_string(),
+        })
+        .insert(QuickPlayComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnBack)
+        .insert(Button {
+            color_bg: Color::MAROON,
+            color_fg: Color::WHITE,
+            position: 4,
+            text: BACK_TXT.to_string(),
+        })
+        .insert(QuickPlayComponent);
+}
+
+fn update_buttons(
+    mut players: Query<&mut Button, With<BtnPlayers>>,
+    preferences: Res<Preferences>,
+    mut search: Query<&mut Button, (With<BtnSearch>, Without<BtnPlayers>)>,
+    mut variant: Query<&mut Button, (With<BtnVariant>, Without<BtnPlayers>, Without<BtnSearch>)>,
+) {
+    let text = match preferences.players {
+        Some(players) => format!("{} {}", PLAYERS_TXT, players),
+        None => format!("{} ANY", PLAYERS_TXT),
+    };
+
+    for mut btn in players.iter_mut() {
+        if btn.text != text {
+            btn.text = text.clone();
+        }
+    }
+
+    let text = match preferences.searching {
+        true => CANCEL_TXT,
+        false => SEARCH_TXT,
+    };
+
+    for mut btn in search.iter_mut() {
+        if btn.text != text {
+            btn.text = text.to_string();
+        }
+    }
+
+    let text = match &preferences.variant {
+        Some(variant) => format!("{} {}", VARIANT_TXT, variant_text(variant)),
+        None => format!("{} ANY", VARIANT_TXT),
+    };
+
+    for mut btn in variant.iter_mut() {
+        if btn.text != text {
+            btn.text = text.clone();
+        }
+    }
+}
+
+fn update_hint(
+    dimensions: Res<Dimensions>,
+    preferences: Res<Preferences>,
+    mut query: Query<(&mut Text, &mut Transform), With<Hint>>,
+) {
+    let text = match preferences.searching {
+        true => SEARCHING_TXT,
+        false => HINT_TXT,
+    };
+
+    for (mut txt, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.to_string();
+        }
+
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 0).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+/// The room screen moves on to the game once the server has seated the local user.
+fn update_match(
+    mut app_state: ResMut<State<AppState>>,
+    client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    players: Query<&Player>,
+) {
+    if own_room(&client, &local_user, &players).is_some() {
+        app_state.set(AppState::Room).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+use bevy::{
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, Plugin, Query, Res, ResMut,
+        Resource, State, SystemSet, Transform, With,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    time::{Time, Timer, TimerMode},
+};
+use durakifa_protocol::protocol::{Protocol, Room, RoomStatus};
+use naia_bevy_client::{shared::DefaultChannels, Client};
+
+use crate::{open_connection, AppState, FontAssets, LocalUser, NetState, ServerChoice};
+
+use super::{
+    dimensions::{Dimensions, GRID_SZE},
+    menu::{Button, ButtonEvent},
+};
+
+const BACKOFF_MAX: f32 = 30.0; // Seconds
+const BACKOFF_MIN: f32 = 1.0; // Seconds
+const CONNECTING_TXT: &str = "Verbinde...";
+const LOST_TXT: &str = "CONNÉCTION LO555T";
+const RETRY_TXT: &str = "RÉTRY NOW";
+const WAIT_TXT: &str = "Verbinde in";
+
+#[derive(Resource)]
+struct Backoff {
+    attempt: u32,
+    timer: Timer,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Backoff {
+            attempt: 0,
+            timer: Timer::from_seconds(BACKOFF_MIN, TimerMode::Once),
+        }
+    }
+
+    fn next(&mut self) {
+        self.attempt += 1;
+        let secs = (BACKOFF_MIN * 2f32.powi(self.attempt as i32)).min(BACKOFF_MAX);
+        self.timer = Timer::from_seconds(secs, TimerMode::Once);
+    }
+}
+
+#[derive(Component)]
+struct BtnRetry;
+
+#[derive(Component)]
+struct Countdown;
+
+#[derive(Component)]
+struct Headline;
+
+#[derive(Component)]
+struct ReconnectComponent;
+
+pub struct ReconnectPlugin;
+impl Plugin for ReconnectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Reconnect).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Reconnect).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Reconnect)
+                    .with_system(input)
+                    .with_system(resume)
+                    .with_system(retry)
+                    .with_system(update_countdown)
+                    .with_system(update_headline),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<ReconnectComponent>>) {
+    commands.remove_resource::<Backoff>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn input(
+    mut backoff: ResMut<Backoff>,
+    btn_retry: Query<&BtnRetry>,
+    mut client: Client<Protocol, DefaultChannels>,
+    mut event_reader: EventReader<ButtonEvent>,
+    local_user: Res<LocalUser>,
+    net_state: Res<State<NetState>>,
+    server: Res<ServerChoice>,
+) {
+    for event in event_reader.iter() {
+        if btn_retry.get(event.entity).is_ok()
+            && vec![NetState::Offline].contains(net_state.current())
+        {
+            *backoff = Backoff::new();
+            open_connection(&mut client, &local_user, &server);
+            return;
+        }
+    }
+}
+
+fn resume(
+    mut app_state: ResMut<State<AppState>>,
+    local_user: Res<LocalUser>,
+    net_state: Res<State<NetState>>,
+    rooms: Query<&Room>,
+) {
+    if !vec![NetState::Online].contains(net_state.current()) {
+        return;
+    }
+
+    // Without a token there is no session to resume, so the user has to register again
+    if local_user.token.is_none() {
+        app_state.set(AppState::Register).unwrap();
+        return;
+    }
+
+    if local_user.entity.is_some() {
+        match local_user.room.map(|room| rooms.get(room)) {
+            Some(Ok(room)) if *room.status == RoomStatus::InGame => {
+                app_state.set(AppState::Game).unwrap()
+            }
+            Some(_) => app_state.set(AppState::Room).unwrap(),
+            None => app_state.set(AppState::Lobby).unwrap(),
+        }
+    }
+}
+
+fn retry(
+    mut backoff: ResMut<Backoff>,
+    mut client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    net_state: Res<State<NetState>>,
+    server: Res<ServerChoice>,
+    time: Res<Time>,
+) {
+    if !backoff.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // Online the resume may just be slow, an expired session comes back with a new token
+    if vec![NetState::Offline].contains(net_state.current()) {
+        backoff.next();
+        open_connection(&mut client, &local_user, &server);
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+    commands.insert_resource(Backoff::new());
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                LOST_TXT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Headline)
+        .insert(ReconnectComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Countdown)
+        .insert(ReconnectComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnRetry)
+        .insert(Button {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 1,
+            text: RETRY_TXT.to_string(),
+        })
+        .insert(ReconnectComponent);
+}
+
+fn update_countdown(
+    backoff: Res<Backoff>,
+    dimensions: Res<Dimensions>,
+    net_state: Res<State<NetState>>,
+    mut query: Query<(&mut Text, &mut Transform), With<Countdown>>,
+) {
+    let text = match net_state.current() {
+        NetState::Offline => format!(
+            "{} {}s",
+            WAIT_TXT,
+            backoff.timer.remaining_secs().ceil() as u32
+        ),
+        NetState::Online => CONNECTING_TXT.to_string(),
+    };
+
+    for (mut txt, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.clone();
+        }
+
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 2).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_headline(
+    dimensions: Res<Dimensions>,
+    mut query: Query<(&mut Text, &mut Transform), With<Headline>>,
+) {
+    for (mut txt, mut tf) in query.iter_mut() {
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 1).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
@@ -7,33 +7,77 @@ use bevy::{
     text::{Text, Text2dBundle, TextAlignment, TextStyle},
     window::ReceivedCharacter,
 };
-use durakifa_protocol::protocol::{Protocol, RegisterUser};
+use durakifa_protocol::protocol::{NameError, Protocol};
 use naia_bevy_client::{shared::DefaultChannels, Client};
 
-use crate::{AppState, FontAssets, InputState};
+use crate::{open_connection, AppState, FontAssets, InputState, LocalUser, ServerChoice};
 
 use super::{
     dimensions::Dimensions,
+    menu::{Button as MenuButton, ButtonEvent},
     vkeyboard::{Button, Key},
 };
 
+const CHARACTERS_TXT: &str = "NAMÉ HA555 INVALID CHARACTÉR555";
+const EMPTY_TXT: &str = "NAMÉ I555 ÉMPTY";
+const GUEST_TXT: &str = "No password: play as guest";
 const NAMESZE: usize = 30;
-const PROMPT: &str = "Enter your Agent ID:";
+const PASSWORDSZE: usize = 64;
+const PLAY_TXT: &str = "PLAY";
+const PROMPT_NAME: &str = "Agent ID:";
+const PROMPT_PASSWORD: &str = "Password:";
+const REJECTED_TXT: &str = "LOGIN RÉJECTÉD";
+const SIGNUP_TXT: &str = "SIGN UP";
+const TAKEN_TXT: &str = "NAMÉ I555 TAKÉN";
+const TOO_LONG_TXT: &str = "NAMÉ I555 TOO LONG";
 
 #[derive(Component)]
-struct Name;
+struct BtnPlay;
 
-#[derive(Default, Resource)]
-struct PlayerLocal {
-    pub name: String,
+#[derive(Component)]
+struct BtnSignUp;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Password,
+}
+
+#[derive(Resource)]
+struct Entry {
+    field: Field,
+    name: String,
+    password: String,
+}
+
+impl Entry {
+    fn pop(&mut self) {
+        match self.field {
+            Field::Name => self.name.pop(),
+            Field::Password => self.password.pop(),
+        };
+    }
+
+    fn push(&mut self, str: &str) {
+        match self.field {
+            Field::Name if self.name.chars().count() < NAMESZE => self.name.push_str(str),
+            Field::Password if self.password.chars().count() < PASSWORDSZE => {
+                self.password.push_str(str)
+            }
+            _ => (),
+        }
+    }
 }
 
 #[derive(Component)]
-struct Prompt;
+struct FieldText(Field);
 
 #[derive(Component)]
 struct RegisterComponent;
 
+#[derive(Component)]
+struct Status;
+
 pub struct RegisterPlugin;
 impl Plugin for RegisterPlugin {
     fn build(&self, app: &mut App) {
@@ -41,29 +85,58 @@ impl Plugin for RegisterPlugin {
             .add_system_set(SystemSet::on_exit(AppState::Register).with_system(cleanup))
             .add_system_set(
                 SystemSet::on_update(AppState::Register)
+                    .with_system(input)
                     .with_system(input_keyboard)
                     .with_system(input_vkeyboard)
-                    .with_system(update_name)
-                    .with_system(update_prompt),
-            )
-            .insert_resource(PlayerLocal::default());
+                    .with_system(update_fields)
+                    .with_system(update_status),
+            );
     }
 }
 
 fn cleanup(mut commands: Commands, query: Query<Entity, With<RegisterComponent>>) {
-    commands.remove_resource::<PlayerLocal>();
+    commands.remove_resource::<Entry>();
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
 }
 
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_play: Query<&BtnPlay>,
+    btn_sign_up: Query<&BtnSignUp>,
+    mut client: Client<Protocol, DefaultChannels>,
+    entry: Res<Entry>,
+    mut event_reader: EventReader<ButtonEvent>,
+    mut local_user: ResMut<LocalUser>,
+    server: Res<ServerChoice>,
+) {
+    for event in event_reader.iter() {
+        let sign_up = btn_sign_up.get(event.entity).is_ok();
+        if sign_up || btn_play.get(event.entity).is_ok() {
+            submit(
+                &mut app_state,
+                &mut client,
+                &entry,
+                &mut local_user,
+                &server,
+                sign_up,
+            );
+
+            return;
+        }
+    }
+}
+
 fn input_keyboard(
     mut app_state: ResMut<State<AppState>>,
     mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
     mut input: ResMut<Input<KeyCode>>,
     mut input_char: EventReader<ReceivedCharacter>,
     input_state: Res<InputState>,
-    mut player: ResMut<PlayerLocal>,
+    mut local_user: ResMut<LocalUser>,
+    server: Res<ServerChoice>,
 ) {
     if !vec![InputState::Keyboard].contains(&input_state) {
         return;
@@ -71,28 +144,36 @@ fn input_keyboard(
 
     if input.pressed(KeyCode::Back) {
         input.release(KeyCode::Back);
+        entry.pop();
+        return;
+    }
 
-        if player.name.len() > 0 {
-            player.name.pop().unwrap();
-        }
-
+    if input.pressed(KeyCode::Tab) {
+        input.release(KeyCode::Tab);
+        toggle(&mut entry);
         return;
     }
 
     if input.pressed(KeyCode::Return) {
         input.release(KeyCode::Return);
-        app_state.set(AppState::Lobby).unwrap();
-        client.send_message(
-            DefaultChannels::UnorderedReliable,
-            &RegisterUser::new(player.name.clone()),
-        );
+        match entry.field {
+            Field::Name => toggle(&mut entry),
+            Field::Password => submit(
+                &mut app_state,
+                &mut client,
+                &entry,
+                &mut local_user,
+                &server,
+                false,
+            ),
+        }
 
         return;
     }
 
     for e in input_char.iter() {
-        if !e.char.is_control() && player.name.len() <= NAMESZE {
-            player.name.push(e.char);
+        if !e.char.is_control() {
+            entry.push(e.char.to_string().as_str());
         }
     }
 }
@@ -100,35 +181,61 @@ fn input_keyboard(
 fn input_vkeyboard(
     mut app_state: ResMut<State<AppState>>,
     mut client: Client<Protocol, DefaultChannels>,
+    mut entry: ResMut<Entry>,
     mut event_reader: EventReader<Button>,
-    mut player: ResMut<PlayerLocal>,
+    mut local_user: ResMut<LocalUser>,
+    server: Res<ServerChoice>,
 ) {
     for btn in event_reader.iter() {
         match btn.key {
-            Key::Backspace => {
-                if player.name.len() > 0 {
-                    player.name.pop().unwrap();
-                }
-            }
-            Key::Return => {
-                app_state.set(AppState::Lobby).unwrap();
-                client.send_message(
-                    DefaultChannels::UnorderedReliable,
-                    &RegisterUser::new(player.name.clone()),
-                );
-            }
-            _ => player.name.push_str(btn.to_string().as_str()),
+            Key::Backspace => entry.pop(),
+            Key::Return => match entry.field {
+                Field::Name => toggle(&mut entry),
+                Field::Password => submit(
+                    &mut app_state,
+                    &mut client,
+                    &entry,
+                    &mut local_user,
+                    &server,
+                    false,
+                ),
+            },
+            _ => entry.push(btn.to_string().as_str()),
         }
     }
 }
 
-fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+fn setup(mut commands: Commands, fonts: Res<FontAssets>, local_user: Res<LocalUser>) {
+    commands.insert_resource(Entry {
+        field: Field::Name,
+        name: local_user.name.clone(),
+        password: String::new(),
+    });
+
+    for field in [Field::Name, Field::Password] {
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        color: Color::CYAN,
+                        font: fonts.regular.clone(),
+                        ..default()
+                    },
+                )
+                .with_alignment(TextAlignment::TOP_CENTER),
+                ..default()
+            })
+            .insert(FieldText(field))
+            .insert(RegisterComponent);
+    }
+
     commands
         .spawn(Text2dBundle {
             text: Text::from_section(
                 "",
                 TextStyle {
-                    color: Color::CYAN,
+                    color: Color::PINK,
                     font: fonts.regular.clone(),
                     ..default()
                 },
@@ -136,55 +243,134 @@ fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
             .with_alignment(TextAlignment::TOP_CENTER),
             ..default()
         })
-        .insert(Name)
+        .insert(Status)
         .insert(RegisterComponent);
 
     commands
-        .spawn(Text2dBundle {
-            text: Text::from_section(
-                PROMPT,
-                TextStyle {
-                    color: Color::PINK,
-                    font: fonts.regular.clone(),
-                    ..default()
-                },
-            )
-            .with_alignment(TextAlignment::TOP_CENTER),
-            ..default()
+        .spawn_empty()
+        .insert(BtnPlay)
+        .insert(MenuButton {
+            color_bg: Color::DARK_GREEN,
+            color_fg: Color::WHITE,
+            position: 3,
+            text: PLAY_TXT.to_string(),
         })
-        .insert(Prompt)
         .insert(RegisterComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnSignUp)
+        .insert(MenuButton {
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
+            position: 4,
+            text: SIGNUP_TXT.to_string(),
+        })
+        .insert(RegisterComponent);
+}
+
+fn submit(
+    app_state: &mut State<AppState>,
+    client: &mut Client<Protocol, DefaultChannels>,
+    entry: &Entry,
+    local_user: &mut LocalUser,
+    server: &ServerChoice,
+    sign_up: bool,
+) {
+    let name = entry.name.trim();
+    if name.is_empty() || (sign_up && entry.password.is_empty()) {
+        return;
+    }
+
+    local_user.idle = false;
+    local_user.name = name.to_string();
+    local_user.name_error = None;
+    local_user.password = match entry.password.is_empty() {
+        true => None,
+        false => Some(entry.password.clone()),
+    };
+    local_user.rejected = false;
+    local_user.sign_up = sign_up;
+    local_user.token = None;
+
+    open_connection(client, local_user, server);
+    app_state.set(AppState::Connect).unwrap();
+}
+
+fn toggle(entry: &mut Entry) {
+    entry.field = match entry.field {
+        Field::Name => Field::Password,
+        Field::Password => Field::Name,
+    };
 }
 
-fn update_name(
+fn update_fields(
     dimensions: Res<Dimensions>,
-    player: Res<PlayerLocal>,
-    mut query: Query<(&mut Text, &mut Transform), With<Name>>,
+    entry: Res<Entry>,
+    mut query: Query<(&FieldText, &mut Text, &mut Transform)>,
 ) {
-    if dimensions.is_changed() {
-        for (mut txt, mut tf) in query.iter_mut() {
-            tf.translation.y = dimensions.translate(0, 1).y;
-            txt.sections[0].style.font_size = 0.25 * dimensions.block;
+    for (field, mut txt, mut tf) in query.iter_mut() {
+        let cursor = match entry.field == field.0 {
+            true => "_",
+            false => "",
+        };
+
+        let (text, position) = match field.0 {
+            Field::Name => (format!("{} {}{}", PROMPT_NAME, entry.name, cursor), 0),
+            Field::Password => (
+                format!(
+                    "{} {}{}",
+                    PROMPT_PASSWORD,
+                    "*".repeat(entry.password.chars().count()),
+                    cursor
+                ),
+                1,
+            ),
+        };
+
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text;
         }
-    }
 
-    if player.is_changed() {
-        for (mut txt, _) in query.iter_mut() {
-            txt.sections[0].value = player.name.clone();
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, position).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
         }
     }
 }
 
-fn update_prompt(
+fn update_status(
     dimensions: Res<Dimensions>,
-    mut query: Query<(&mut Text, &mut Transform), With<Prompt>>,
+    local_user: Res<LocalUser>,
+    mut query: Query<(&mut Text, &mut Transform), With<Status>>,
 ) {
-    if !dimensions.is_changed() {
-        return;
-    }
+    let text = match (&local_user.name_error, local_user.rejected) {
+        (Some(NameError::Characters), _) => CHARACTERS_TXT,
+        (Some(NameError::Empty), _) => EMPTY_TXT,
+        (Some(NameError::Taken), _) => TAKEN_TXT,
+        (Some(NameError::TooLong), _) => TOO_LONG_TXT,
+        (None, true) => REJECTED_TXT,
+        (None, false) => GUEST_TXT,
+    };
 
     for (mut txt, mut tf) in query.iter_mut() {
-        tf.translation.y = dimensions.translate(0, 0).y;
-        txt.sections[0].style.font_size = 0.25 * dimensions.block;
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.to_string();
+        }
+
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 2).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
     }
 }
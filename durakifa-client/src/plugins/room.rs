@@ -1,64 +1,165 @@
 use bevy::{
+    ecs::query::ReadOnlyWorldQuery,
     math::{Vec2, Vec3},
     prelude::{
-        default, App, Changed, Color, Commands, Component, Entity, EventReader, Local, Plugin,
-        Query, Res, ResMut, State, SystemSet, Transform, With, Without,
+        default, App, Changed, Color, Commands, Component, Entity, EventReader, EventWriter,
+        Plugin, Query, Res, ResMut, Resource, State, SystemSet, Transform, With, Without,
     },
     sprite::SpriteBundle,
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
 };
-use durakifa_protocol::protocol::{LeaveRoom, Name, Owner, Player, Protocol};
-use naia_bevy_client::{shared::DefaultChannels, Client};
+use durakifa_protocol::protocol::{
+    Idleness, KickPlayer, LeaveRoom, MoveSeat, Name, Owner, Player, Protocol, Ready, Rejection,
+    Room, RoomStatus, SetReady, Spectator, StartGame, TransferOwnership,
+};
+use naia_bevy_client::{events::MessageEvent, shared::DefaultChannels, Client};
 
-use crate::{AppState, ImageAssets};
+use crate::{AppState, FontAssets, ImageAssets, LocalUser};
 
 use super::{
+    chat::ChatScope,
     dimensions::{Dimensions, GRID_SZE},
     menu::{Button, ButtonEvent},
+    toast::ToastEvent,
 };
 
+const BAN_TXT: &str = "BAN";
+const CHAT_TXT: &str = "CHAT";
+const CLOSED_TXT: &str = "ROOM CLO555ÉD";
+const BANNED_TXT: &str = "BANNÉD FROM ROOM";
+const CANCEL_TXT: &str = "CANCÉL";
+const CODE_TXT: &str = "Invite code:";
+const FAILED_TXT: &str = "SÉRVÉR ÉRROR, TRY AGAIN";
+const FULL_TXT: &str = "ROOM I555 FULL";
+const IDLE_CLOSED_TXT: &str = "ROOM CLO555ÉD: NO GAMÉ";
+const IDLE_DISCONNECTED_TXT: &str = "DI555CONNÉCTÉD: IDLÉ";
+const IDLE_MOVED_TXT: &str = "MOVÉD TO LOBBY: IDLÉ";
+const IDLE_WARNING_TXT: &str = "IDLÉ: ACT WITHIN";
+const INPROGRESS_TXT: &str = "GAMÉ IN PROGRÉ555";
+const KICK_TXT: &str = "KICK";
+const KICKED_TXT: &str = "KICKÉD FROM ROOM";
 const LEAVEGAME_TXT: &str = "LÉAVE GAMÉ";
+const NOTFOUND_TXT: &str = "ROOM I555 GONÉ";
+const OWNER_TXT: &str = "MAKÉ OWNÉR";
+const PASSWORD_TXT: &str = "WRONG PA555WORD";
+const READY_TXT: &str = "I'M RÉADY";
+const SHUTDOWN_TXT: &str = "SÉRVÉR RÉ555TART IN";
 const STARTGAME_TXT: &str = "555TART GAMÉ";
+const UNREADY_TXT: &str = "NOT RÉADY";
+const WATCHING_TXT: &str = "Watching:";
+
+/// Context actions the owner can take on a selected player.
+#[derive(Clone, Component, Copy)]
+enum Action {
+    Ban,
+    Cancel,
+    Kick,
+    Owner,
+}
 
 #[derive(Component)]
 struct BtnLeave;
 
 #[derive(Component)]
-struct BtnStart;
+struct BtnChat;
+
+/// Starts the game for the owner and toggles readiness for everyone else.
+#[derive(Component)]
+struct BtnReady;
+
+#[derive(Component)]
+struct Control;
 
 #[derive(Component)]
 struct Crown;
 
+#[derive(Component)]
+struct InviteText;
+
+/// Shown beside the name of a ready player.
+#[derive(Component)]
+struct ReadyMark(Entity);
+
 #[derive(Component)]
 struct RoomComponent;
 
+/// The player the owner opened the context actions for.
+#[derive(Resource)]
+struct Selection(Option<Entity>);
+
+#[derive(Component)]
+struct SpectatorText;
+
+/// The room the local user watches as a spectator.
+#[derive(Resource)]
+struct Watching(Option<Entity>);
+
 pub struct RoomPlugin;
 impl Plugin for RoomPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(AppState::Room).with_system(setup))
+        app.add_system(idle)
+            .add_system(kicked)
+            .add_system(shutdown)
+            .add_system_set(SystemSet::on_enter(AppState::Room).with_system(setup))
             .add_system_set(SystemSet::on_exit(AppState::Room).with_system(cleanup))
             .add_system_set(
                 SystemSet::on_update(AppState::Room)
                     .with_system(input)
+                    .with_system(reject)
+                    .with_system(update_code)
                     .with_system(update_owner)
                     .with_system(update_player_names)
-                    .with_system(update_players),
+                    .with_system(update_players)
+                    .with_system(update_ready)
+                    .with_system(update_ready_button)
+                    .with_system(update_spectators)
+                    .with_system(update_status)
+                    .with_system(update_watching),
             );
     }
 }
 
 fn cleanup(mut commands: Commands, query: Query<Entity, With<RoomComponent>>) {
+    commands.remove_resource::<Selection>();
+    commands.remove_resource::<Watching>();
     for entity in query.iter() {
         commands.entity(entity).despawn();
     }
 }
 
+fn close_actions(actions: &Query<(Entity, &Action)>, commands: &mut Commands) {
+    for (entity, _) in actions.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    spawn_controls(commands);
+}
+
 fn input(
+    actions: Query<(Entity, &Action)>,
     mut app_state: ResMut<State<AppState>>,
+    btn_chat: Query<&BtnChat>,
     btn_leave: Query<&BtnLeave>,
-    btn_start: Query<&BtnStart>,
+    btn_ready: Query<&BtnReady>,
     mut client: Client<Protocol, DefaultChannels>,
+    mut commands: Commands,
+    controls: Query<Entity, With<Control>>,
     mut event_reader: EventReader<ButtonEvent>,
+    local_user: Res<LocalUser>,
+    owners: Query<&Player, With<Owner>>,
+    players: Query<&Player, With<Button>>,
+    readies: Query<&Player, With<Ready>>,
+    mut selection: ResMut<Selection>,
 ) {
+    // The selected player left the room
+    if let Some(player) = selection.0 {
+        if !players.contains(player) {
+            selection.0 = None;
+            close_actions(&actions, &mut commands);
+            return;
+        }
+    }
+
     for event in event_reader.iter() {
         if btn_leave.get(event.entity).is_ok() {
             app_state.set(AppState::Lobby).unwrap();
@@ -66,14 +167,204 @@ fn input(
             return;
         }
 
-        if btn_start.get(event.entity).is_ok() {
-            app_state.set(AppState::Game).unwrap();
+        if btn_chat.get(event.entity).is_ok() {
+            commands.insert_resource(ChatScope { room: true });
+            app_state.set(AppState::Chat).unwrap();
+            return;
+        }
+
+        // The server starts the game once the owner asks and all other players are ready
+        if btn_ready.get(event.entity).is_ok() {
+            if own_room(&client, &local_user, &owners).is_some() {
+                client.send_message(DefaultChannels::UnorderedReliable, &StartGame::new());
+                return;
+            }
+
+            let ready = own_room(&client, &local_user, &readies).is_some();
+            client.send_message(DefaultChannels::UnorderedReliable, &SetReady::new(!ready));
+            return;
+        }
+
+        if let Ok((_, action)) = actions.get(event.entity) {
+            if let Some(player) = selection.0.take() {
+                match action {
+                    Action::Ban | Action::Kick => {
+                        let mut kick = KickPlayer::new(matches!(action, Action::Ban));
+                        kick.player.set(&client, &player);
+                        client.send_message(DefaultChannels::UnorderedReliable, &kick);
+                    }
+                    Action::Cancel => (),
+                    Action::Owner => {
+                        let mut transfer = TransferOwnership::new();
+                        transfer.player.set(&client, &player);
+                        client.send_message(DefaultChannels::UnorderedReliable, &transfer);
+                    }
+                }
+            }
+
+            close_actions(&actions, &mut commands);
+            return;
+        }
+
+        // Only the owner gets context actions. Picking another player while one is selected
+        // moves the selected player to that seat.
+        if let Ok(player) = players.get(event.entity) {
+            if own_room(&client, &local_user, &owners).is_none() {
+                return;
+            }
+
+            match selection.0.take() {
+                Some(selected) => {
+                    if selected != event.entity {
+                        let mut move_seat = MoveSeat::new(*player.seat);
+                        move_seat.player.set(&client, &selected);
+                        client.send_message(DefaultChannels::UnorderedReliable, &move_seat);
+                    }
+
+                    close_actions(&actions, &mut commands);
+                }
+                None => {
+                    selection.0 = Some(event.entity);
+                    for entity in controls.iter() {
+                        commands.entity(entity).despawn();
+                    }
+
+                    spawn_actions(&mut commands, player.user.get(&client) != local_user.entity);
+                }
+            }
+
+            return;
+        }
+    }
+}
+
+fn idle(
+    mut app_state: ResMut<State<AppState>>,
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut local_user: ResMut<LocalUser>,
+    mut toasts: EventWriter<ToastEvent>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::IdleNotice(msg)) = event {
+            let text = match *msg.kind {
+                Idleness::Closed => IDLE_CLOSED_TXT.to_string(),
+                Idleness::Disconnected => {
+                    // The server disconnects right after, so the session is not to be resumed
+                    local_user.idle = true;
+                    local_user.token = None;
+                    IDLE_DISCONNECTED_TXT.to_string()
+                }
+                Idleness::Moved => IDLE_MOVED_TXT.to_string(),
+                Idleness::Warning => format!("{} {} 555ÉC", IDLE_WARNING_TXT, *msg.seconds),
+            };
+
+            toasts.send(ToastEvent { text });
+            if matches!(*msg.kind, Idleness::Closed | Idleness::Moved)
+                && vec![AppState::Game, AppState::Room].contains(app_state.current())
+            {
+                app_state.set(AppState::Lobby).unwrap();
+            }
+        }
+    }
+}
+
+fn kicked(
+    mut app_state: ResMut<State<AppState>>,
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut toasts: EventWriter<ToastEvent>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::Kicked(msg)) = event {
+            let text = match *msg.banned {
+                true => BANNED_TXT,
+                false => KICKED_TXT,
+            };
+
+            toasts.send(ToastEvent {
+                text: text.to_string(),
+            });
+            if vec![AppState::Game, AppState::Room].contains(app_state.current()) {
+                app_state.set(AppState::Lobby).unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the room the local user is seated in, if any.
+pub fn own_room<F: ReadOnlyWorldQuery>(
+    client: &Client<Protocol, DefaultChannels>,
+    local_user: &LocalUser,
+    players: &Query<&Player, F>,
+) -> Option<Entity> {
+    players
+        .iter()
+        .find(|player| local_user.entity.is_some() && player.user.get(client) == local_user.entity)
+        .and_then(|player| player.room.get(client))
+}
+
+fn reject(
+    mut app_state: ResMut<State<AppState>>,
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut toasts: EventWriter<ToastEvent>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::JoinRejected(msg)) = event {
+            let text = match *msg.reason {
+                Rejection::Banned => BANNED_TXT,
+                Rejection::Failed => FAILED_TXT,
+                Rejection::Full => FULL_TXT,
+                Rejection::InProgress => INPROGRESS_TXT,
+                Rejection::NotFound => NOTFOUND_TXT,
+                Rejection::Password => PASSWORD_TXT,
+            };
+
+            toasts.send(ToastEvent {
+                text: text.to_string(),
+            });
+            app_state.set(AppState::Lobby).unwrap();
             return;
         }
     }
 }
 
-fn setup(mut commands: Commands, dimensions: Res<Dimensions>, images: Res<ImageAssets>) {
+fn setup(
+    mut commands: Commands,
+    dimensions: Res<Dimensions>,
+    fonts: Res<FontAssets>,
+    images: Res<ImageAssets>,
+) {
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(InviteText)
+        .insert(RoomComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::GRAY,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(SpectatorText)
+        .insert(RoomComponent);
+
     commands
         .spawn(SpriteBundle {
             sprite: bevy::sprite::Sprite {
@@ -87,6 +378,51 @@ fn setup(mut commands: Commands, dimensions: Res<Dimensions>, images: Res<ImageA
         .insert(Crown)
         .insert(RoomComponent);
 
+    commands.insert_resource(Selection(None));
+    commands.insert_resource(Watching(None));
+    spawn_controls(&mut commands);
+}
+
+/// The server comes back with the game, the client reconnects on its own.
+fn shutdown(
+    mut event_reader: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut toasts: EventWriter<ToastEvent>,
+) {
+    for event in event_reader.iter() {
+        if let MessageEvent(_, Protocol::ShutdownNotice(msg)) = event {
+            toasts.send(ToastEvent {
+                text: format!("{} {} 555ÉC", SHUTDOWN_TXT, *msg.seconds),
+            });
+        }
+    }
+}
+
+/// The owner can only move itself to another seat, so it gets no actions but cancel.
+fn spawn_actions(commands: &mut Commands, other: bool) {
+    for (action, color_bg, position, text) in [
+        (Action::Owner, Color::DARK_GREEN, GRID_SZE - 4, OWNER_TXT),
+        (Action::Kick, Color::MAROON, GRID_SZE - 3, KICK_TXT),
+        (Action::Ban, Color::MAROON, GRID_SZE - 2, BAN_TXT),
+        (Action::Cancel, Color::DARK_GRAY, GRID_SZE - 1, CANCEL_TXT),
+    ] {
+        if !other && !matches!(action, Action::Cancel) {
+            continue;
+        }
+
+        commands
+            .spawn_empty()
+            .insert(action)
+            .insert(Button {
+                color_bg,
+                color_fg: Color::WHITE,
+                position,
+                text: text.to_string(),
+            })
+            .insert(RoomComponent);
+    }
+}
+
+fn spawn_controls(commands: &mut Commands) {
     commands
         .spawn_empty()
         .insert(BtnLeave)
@@ -96,20 +432,67 @@ fn setup(mut commands: Commands, dimensions: Res<Dimensions>, images: Res<ImageA
             position: GRID_SZE - 1,
             text: LEAVEGAME_TXT.to_string(),
         })
+        .insert(Control)
         .insert(RoomComponent);
 
     commands
         .spawn_empty()
-        .insert(BtnStart)
+        .insert(BtnReady)
         .insert(Button {
-            color_bg: Color::DARK_GREEN,
-            color_fg: Color::WHITE,
+            color_bg: Color::MIDNIGHT_BLUE,
+            color_fg: Color::YELLOW,
             position: GRID_SZE - 2,
-            text: STARTGAME_TXT.to_string(),
+            text: READY_TXT.to_string(),
+        })
+        .insert(Control)
+        .insert(RoomComponent);
+
+    commands
+        .spawn_empty()
+        .insert(BtnChat)
+        .insert(Button {
+            color_bg: Color::DARK_GRAY,
+            color_fg: Color::WHITE,
+            position: GRID_SZE - 3,
+            text: CHAT_TXT.to_string(),
         })
+        .insert(Control)
         .insert(RoomComponent);
 }
 
+/// Only the owner gets to see the invite code.
+fn update_code(
+    client: Client<Protocol, DefaultChannels>,
+    dimensions: Res<Dimensions>,
+    local_user: Res<LocalUser>,
+    players: Query<&Player, With<Owner>>,
+    mut query: Query<(&mut Text, &mut Transform), With<InviteText>>,
+    rooms: Query<&Room>,
+    selection: Res<Selection>,
+) {
+    // The context actions cover the invite code
+    let text = match own_room(&client, &local_user, &players).map(|room| rooms.get(room)) {
+        Some(Ok(room)) if selection.0.is_none() => format!("{} {}", CODE_TXT, *room.code),
+        _ => String::new(),
+    };
+
+    for (mut txt, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.clone();
+        }
+
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, GRID_SZE - 4).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
 fn update_owner(
     dimensions: Res<Dimensions>,
     mut crown: Query<&mut Transform, With<Crown>>,
@@ -152,18 +535,16 @@ fn update_player_names(
 }
 
 fn update_players(
-    mut buttons: Query<&mut Button>,
+    mut buttons: Query<(&mut Button, &Player)>,
     client: Client<Protocol, DefaultChannels>,
     mut commands: Commands,
     names: Query<&Name>,
-    mut players: Local<Vec<Entity>>,
     query: Query<(Entity, &Player), Without<Button>>,
 ) {
-    let len = players.len();
-    players.retain(|entity| buttons.contains(*entity));
-    if players.len() != len {
-        for (i, entity) in players.iter().enumerate() {
-            buttons.get_mut(*entity).unwrap().position = i;
+    for (mut btn, player) in buttons.iter_mut() {
+        let position = *player.seat as usize;
+        if btn.position != position {
+            btn.position = position;
         }
     }
 
@@ -173,12 +554,178 @@ fn update_players(
                 commands.entity(entity).insert(Button {
                     color_bg: Color::MIDNIGHT_BLUE,
                     color_fg: Color::PINK,
-                    position: players.len(),
+                    position: *player.seat as usize,
                     text: (*name.name).clone(),
                 });
+            }
+        }
+    }
+}
 
-                players.push(entity);
+fn update_ready(
+    mut commands: Commands,
+    dimensions: Res<Dimensions>,
+    mut marks: Query<(Entity, &ReadyMark, &mut Transform)>,
+    players: Query<(Entity, &Button), (With<Player>, With<Ready>)>,
+) {
+    for (entity, mark, mut tf) in marks.iter_mut() {
+        let btn = match players.get(mark.0) {
+            Ok((_, btn)) => btn,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
             }
+        };
+
+        let scale = Vec2::splat(0.4 * dimensions.block).extend(tf.scale.z);
+        if tf.scale != scale {
+            tf.scale = scale;
+        }
+
+        let translation = dimensions
+            .translate(0, btn.position)
+            .extend(tf.translation.z);
+
+        if tf.translation != translation {
+            tf.translation = translation;
         }
     }
+
+    for (player, _) in players.iter() {
+        if marks.iter().all(|(_, mark, _)| mark.0 != player) {
+            commands
+                .spawn(SpriteBundle {
+                    sprite: bevy::sprite::Sprite {
+                        color: Color::LIME_GREEN,
+                        custom_size: Some(Vec2::ONE),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                    ..default()
+                })
+                .insert(ReadyMark(player))
+                .insert(RoomComponent);
+        }
+    }
+}
+
+fn update_ready_button(
+    client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    owners: Query<&Player, With<Owner>>,
+    mut query: Query<&mut Button, With<BtnReady>>,
+    readies: Query<&Player, With<Ready>>,
+) {
+    let (color_bg, color_fg, text) = if own_room(&client, &local_user, &owners).is_some() {
+        (Color::DARK_GREEN, Color::WHITE, STARTGAME_TXT)
+    } else if own_room(&client, &local_user, &readies).is_some() {
+        (Color::MIDNIGHT_BLUE, Color::YELLOW, UNREADY_TXT)
+    } else {
+        (Color::MIDNIGHT_BLUE, Color::YELLOW, READY_TXT)
+    };
+
+    for mut btn in query.iter_mut() {
+        if btn.text != text {
+            btn.color_bg = color_bg;
+            btn.color_fg = color_fg;
+            btn.text = text.to_string();
+        }
+    }
+}
+
+/// Lists the spectators by name, apart from the seated players.
+fn update_spectators(
+    client: Client<Protocol, DefaultChannels>,
+    dimensions: Res<Dimensions>,
+    names: Query<&Name>,
+    mut query: Query<(&mut Text, &mut Transform), With<SpectatorText>>,
+    spectators: Query<&Spectator>,
+) {
+    let watchers = spectators
+        .iter()
+        .filter_map(|spectator| spectator.user.get(&client))
+        .filter_map(|user| names.get(user).ok())
+        .map(|name| (*name.name).clone())
+        .collect::<Vec<_>>();
+
+    let text = match watchers.is_empty() {
+        true => String::new(),
+        false => format!("{} {}", WATCHING_TXT, watchers.join(", ")),
+    };
+
+    for (mut txt, mut tf) in query.iter_mut() {
+        if txt.sections[0].value != text {
+            txt.sections[0].value = text.clone();
+        }
+
+        let font_size = 0.2 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, GRID_SZE - 4).y - 0.5 * dimensions.block;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_status(
+    mut app_state: ResMut<State<AppState>>,
+    client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    players: Query<&Player>,
+    rooms: Query<&Room>,
+    spectators: Query<&Spectator>,
+) {
+    let room = own_room(&client, &local_user, &players)
+        .or_else(|| watched_room(&client, &local_user, &spectators));
+
+    if let Some(room) = room {
+        if let Ok(room) = rooms.get(room) {
+            if *room.status == RoomStatus::InGame {
+                app_state.set(AppState::Game).unwrap();
+            }
+        }
+    }
+}
+
+/// Spectators are sent back to the lobby once the room they watch closes.
+fn update_watching(
+    mut app_state: ResMut<State<AppState>>,
+    client: Client<Protocol, DefaultChannels>,
+    local_user: Res<LocalUser>,
+    spectators: Query<&Spectator>,
+    mut toasts: EventWriter<ToastEvent>,
+    mut watching: ResMut<Watching>,
+) {
+    match watched_room(&client, &local_user, &spectators) {
+        Some(room) => {
+            if watching.0 != Some(room) {
+                watching.0 = Some(room);
+            }
+        }
+        None => {
+            if watching.0.take().is_some() {
+                toasts.send(ToastEvent {
+                    text: CLOSED_TXT.to_string(),
+                });
+                app_state.set(AppState::Lobby).unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the room the local user watches, if any.
+fn watched_room(
+    client: &Client<Protocol, DefaultChannels>,
+    local_user: &LocalUser,
+    spectators: &Query<&Spectator>,
+) -> Option<Entity> {
+    spectators
+        .iter()
+        .find(|spectator| {
+            local_user.entity.is_some() && spectator.user.get(client) == local_user.entity
+        })
+        .and_then(|spectator| spectator.room.get(client))
 }
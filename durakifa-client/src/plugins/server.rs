@@ -0,0 +1,366 @@
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::PathBuf};
+
+use bevy::{
+    input::Input,
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, KeyCode, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, Transform, With,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    window::ReceivedCharacter,
+};
+
+use durakifa_protocol::protocol::TICK_DEFAULT;
+
+use crate::{AppState, FontAssets, InputState, ServerChoice, Tick};
+
+use super::{
+    dimensions::Dimensions,
+    menu::{Button, ButtonEvent},
+    vkeyboard::{Button as KeyButton, Key},
+};
+
+const PROMPT: &str = "Enter the server address:";
+const SRV_DEFAULT: &str = "http://127.0.0.1:55500";
+const SRV_HISTORY: usize = 3;
+#[cfg(target_arch = "wasm32")]
+const SRV_HISTORY_KEY: &str = "durakifa.servers";
+const URLSZE: usize = 60;
+
+#[derive(Component)]
+struct Address;
+
+#[derive(Component)]
+struct BtnServer {
+    url: String,
+}
+
+#[derive(Default, Resource)]
+struct Entry {
+    url: String,
+}
+
+#[derive(Component)]
+struct Prompt;
+
+#[derive(Component)]
+struct ServerComponent;
+
+pub struct ServerPlugin;
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Server).with_system(setup))
+            .add_system_set(SystemSet::on_exit(AppState::Server).with_system(cleanup))
+            .add_system_set(
+                SystemSet::on_update(AppState::Server)
+                    .with_system(input)
+                    .with_system(input_keyboard)
+                    .with_system(input_vkeyboard)
+                    .with_system(update_address)
+                    .with_system(update_prompt),
+            );
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<ServerComponent>>) {
+    commands.remove_resource::<Entry>();
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn choose(
+    app_state: &mut State<AppState>,
+    commands: &mut Commands,
+    server: &mut ServerChoice,
+    tick: &mut Tick,
+    url: &str,
+) {
+    let url = url.trim();
+    if url.is_empty() {
+        return;
+    }
+
+    server.url = match url.contains("://") {
+        true => url.to_string(),
+        false => format!("http://{}", url),
+    };
+
+    // The client takes on the tick the server told last time before it connects
+    let mut history = load_history();
+    tick.server = history
+        .iter()
+        .find(|(url, _)| *url == server.url)
+        .map_or(TICK_DEFAULT, |(_, tick)| *tick);
+    tick.apply(commands);
+
+    history.retain(|(url, _)| *url != server.url);
+    history.insert(0, (server.url.clone(), tick.server));
+    history.truncate(SRV_HISTORY);
+    save_history(&history);
+    app_state.set(AppState::Register).unwrap();
+}
+
+fn input(
+    mut app_state: ResMut<State<AppState>>,
+    btn_server: Query<&BtnServer>,
+    mut commands: Commands,
+    mut event_reader: EventReader<ButtonEvent>,
+    mut server: ResMut<ServerChoice>,
+    mut tick: ResMut<Tick>,
+) {
+    for event in event_reader.iter() {
+        if let Ok(btn) = btn_server.get(event.entity) {
+            choose(
+                &mut app_state,
+                &mut commands,
+                &mut server,
+                &mut tick,
+                &btn.url,
+            );
+
+            return;
+        }
+    }
+}
+
+fn input_keyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    mut entry: ResMut<Entry>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut input_char: EventReader<ReceivedCharacter>,
+    input_state: Res<InputState>,
+    mut server: ResMut<ServerChoice>,
+    mut tick: ResMut<Tick>,
+) {
+    if !vec![InputState::Keyboard].contains(&input_state) {
+        return;
+    }
+
+    if input.pressed(KeyCode::Back) {
+        input.release(KeyCode::Back);
+        entry.url.pop();
+        return;
+    }
+
+    if input.pressed(KeyCode::Return) {
+        input.release(KeyCode::Return);
+        choose(
+            &mut app_state,
+            &mut commands,
+            &mut server,
+            &mut tick,
+            &entry.url,
+        );
+
+        return;
+    }
+
+    for e in input_char.iter() {
+        if !e.char.is_control() && entry.url.len() <= URLSZE {
+            entry.url.push(e.char);
+        }
+    }
+}
+
+fn input_vkeyboard(
+    mut app_state: ResMut<State<AppState>>,
+    mut commands: Commands,
+    mut entry: ResMut<Entry>,
+    mut event_reader: EventReader<KeyButton>,
+    mut server: ResMut<ServerChoice>,
+    mut tick: ResMut<Tick>,
+) {
+    for btn in event_reader.iter() {
+        match btn.key {
+            Key::Backspace => {
+                entry.url.pop();
+            }
+            Key::Return => choose(
+                &mut app_state,
+                &mut commands,
+                &mut server,
+                &mut tick,
+                &entry.url,
+            ),
+            _ if entry.url.len() <= URLSZE => entry.url.push_str(btn.to_string().as_str()),
+            _ => (),
+        }
+    }
+}
+
+fn format_history(history: &[(String, Duration)]) -> String {
+    history
+        .iter()
+        .map(|(url, tick)| format!("{} {}", url, tick.as_millis()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn history_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "durakifa").map(|d| d.config_dir().join("servers"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_history() -> Vec<(String, Duration)> {
+    history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|s| parse_history(&s))
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_history() -> Vec<(String, Duration)> {
+    web_sys::window()
+        .and_then(|wnd| wnd.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(SRV_HISTORY_KEY).ok().flatten())
+        .map(|s| parse_history(&s))
+        .unwrap_or_default()
+}
+
+/// One server per line, its address followed by the milliseconds of its tick.
+fn parse_history(s: &str) -> Vec<(String, Duration)> {
+    s.lines()
+        .map(|line| match line.split_once(' ') {
+            Some((url, tick)) => (
+                url.to_string(),
+                tick.parse().map_or(TICK_DEFAULT, Duration::from_millis),
+            ),
+            None => (line.to_string(), TICK_DEFAULT),
+        })
+        .collect()
+}
+
+/// Saves the tick `url` told, so the next visit connects with it right away.
+pub fn remember_tick(url: &str, tick: Duration) {
+    let mut history = load_history();
+    for (known, told) in history.iter_mut() {
+        if known == url {
+            *told = tick;
+        }
+    }
+
+    save_history(&history);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_history(history: &[(String, Duration)]) {
+    if let Some(path) = history_path() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+
+        fs::write(path, format_history(history)).ok();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_history(history: &[(String, Duration)]) {
+    if let Some(storage) = web_sys::window().and_then(|wnd| wnd.local_storage().ok().flatten()) {
+        storage
+            .set_item(SRV_HISTORY_KEY, &format_history(history))
+            .ok();
+    }
+}
+
+fn setup(mut commands: Commands, fonts: Res<FontAssets>) {
+    let mut history = load_history()
+        .into_iter()
+        .map(|(url, _)| url)
+        .collect::<Vec<_>>();
+    if history.is_empty() {
+        history.push(SRV_DEFAULT.to_string());
+    }
+
+    commands.insert_resource(Entry {
+        url: history[0].clone(),
+    });
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    color: Color::CYAN,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Address)
+        .insert(ServerComponent);
+
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                PROMPT,
+                TextStyle {
+                    color: Color::PINK,
+                    font: fonts.regular.clone(),
+                    ..default()
+                },
+            )
+            .with_alignment(TextAlignment::TOP_CENTER),
+            ..default()
+        })
+        .insert(Prompt)
+        .insert(ServerComponent);
+
+    for (i, url) in history.into_iter().enumerate() {
+        commands
+            .spawn_empty()
+            .insert(Button {
+                color_bg: Color::MIDNIGHT_BLUE,
+                color_fg: Color::YELLOW,
+                position: 2 + i,
+                text: url.clone(),
+            })
+            .insert(BtnServer { url })
+            .insert(ServerComponent);
+    }
+}
+
+fn update_address(
+    dimensions: Res<Dimensions>,
+    entry: Res<Entry>,
+    mut query: Query<(&mut Text, &mut Transform), With<Address>>,
+) {
+    for (mut txt, mut tf) in query.iter_mut() {
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        if txt.sections[0].value != entry.url {
+            txt.sections[0].value = entry.url.clone();
+        }
+
+        let translation = dimensions.translate(0, 1).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
+
+fn update_prompt(
+    dimensions: Res<Dimensions>,
+    mut query: Query<(&mut Text, &mut Transform), With<Prompt>>,
+) {
+    for (mut txt, mut tf) in query.iter_mut() {
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, 0).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+    }
+}
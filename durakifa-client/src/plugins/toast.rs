@@ -0,0 +1,97 @@
+use bevy::{
+    prelude::{
+        default, App, Color, Commands, Component, Entity, EventReader, Plugin, Query, Res,
+        Transform, With,
+    },
+    text::{Text, Text2dBundle, TextAlignment, TextStyle},
+    time::{Time, Timer, TimerMode},
+};
+
+use crate::FontAssets;
+
+use super::dimensions::{Dimensions, GRID_SZE};
+
+const TOAST_SECS: f32 = 3.0;
+
+/// Shows `text` on top of whatever screen is active for a few seconds.
+pub struct ToastEvent {
+    pub text: String,
+}
+
+#[derive(Component)]
+struct Toast {
+    timer: Timer,
+}
+
+pub struct ToastPlugin;
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ToastEvent>()
+            .add_system(expire)
+            .add_system(spawn)
+            .add_system(update);
+    }
+}
+
+fn expire(mut commands: Commands, mut query: Query<(Entity, &mut Toast)>, time: Res<Time>) {
+    for (entity, mut toast) in query.iter_mut() {
+        if toast.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn spawn(
+    mut commands: Commands,
+    mut event_reader: EventReader<ToastEvent>,
+    fonts: Option<Res<FontAssets>>,
+    query: Query<Entity, With<Toast>>,
+) {
+    // Fonts are only available once loading has finished
+    let fonts = match fonts {
+        Some(fonts) => fonts,
+        None => return,
+    };
+
+    for event in event_reader.iter() {
+        for entity in query.iter() {
+            commands.entity(entity).despawn();
+        }
+
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    event.text.clone(),
+                    TextStyle {
+                        color: Color::ORANGE,
+                        font: fonts.regular.clone(),
+                        ..default()
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                ..default()
+            })
+            .insert(Toast {
+                timer: Timer::from_seconds(TOAST_SECS, TimerMode::Once),
+            });
+    }
+}
+
+fn update(dimensions: Res<Dimensions>, mut query: Query<(&mut Text, &mut Transform), With<Toast>>) {
+    for (mut txt, mut tf) in query.iter_mut() {
+        let font_size = 0.25 * dimensions.block;
+        if txt.sections[0].style.font_size != font_size {
+            txt.sections[0].style.font_size = font_size;
+        }
+
+        let translation = dimensions.translate(0, GRID_SZE / 2).y;
+        if tf.translation.y != translation {
+            tf.translation.y = translation;
+        }
+
+        // Keep the toast above the menu
+        if tf.translation.z != 1.0 {
+            tf.translation.z = 1.0;
+        }
+    }
+}
@@ -700,26 +700,35 @@ pub enum Key {
 pub struct VKeyboardPlugin;
 impl Plugin for VKeyboardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<Button>()
-            .add_system_set(SystemSet::on_enter(AppState::Register).with_system(setup))
-            .add_system_set(SystemSet::on_exit(AppState::Register).with_system(cleanup))
-            .add_system_set(
-                SystemSet::on_update(AppState::Register)
-                    .with_system(
-                        input_mouse
-                            .after(InputState::Keyboard)
-                            .after(InputState::Mouse),
-                    )
-                    .with_system(listen_buttons)
-                    .with_system(
-                        update_buttons
-                            .after(InputState::Keyboard)
-                            .after(InputState::Mouse),
-                    ),
-            );
+        add_vkeyboard_to_state(app, AppState::Chat);
+        add_vkeyboard_to_state(app, AppState::Code);
+        add_vkeyboard_to_state(app, AppState::Filter);
+        add_vkeyboard_to_state(app, AppState::NewRoom);
+        add_vkeyboard_to_state(app, AppState::Register);
+        add_vkeyboard_to_state(app, AppState::Server);
+        app.add_event::<Button>();
     }
 }
 
+fn add_vkeyboard_to_state(app: &mut App, state: AppState) {
+    app.add_system_set(SystemSet::on_enter(state.clone()).with_system(setup))
+        .add_system_set(SystemSet::on_exit(state.clone()).with_system(cleanup))
+        .add_system_set(
+            SystemSet::on_update(state.clone())
+                .with_system(
+                    input_mouse
+                        .after(InputState::Keyboard)
+                        .after(InputState::Mouse),
+                )
+                .with_system(listen_buttons)
+                .with_system(
+                    update_buttons
+                        .after(InputState::Keyboard)
+                        .after(InputState::Mouse),
+                ),
+        );
+}
+
 #[derive(Clone, Copy)]
 enum Case {
     Lower,
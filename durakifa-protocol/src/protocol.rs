@@ -2,25 +2,87 @@ mod components;
 mod messages;
 
 pub use self::{
-    components::{name::Name, owner::Owner, player::Player, room::Room, user::User},
+    components::{
+        bot::Bot,
+        guest::Guest,
+        name::Name,
+        online::Online,
+        owner::Owner,
+        player::Player,
+        ready::Ready,
+        room::{Room, RoomStatus, Variant},
+        room_summary::RoomSummary,
+        spectator::Spectator,
+        user::User,
+    },
+    messages::chat::{chat::Chat, send_chat::SendChat},
+    messages::game::start_game::StartGame,
     messages::lobby::{
-        authorize::Authorize, create_room::CreateRoom, join_room::JoinRoom, leave_room::LeaveRoom,
-        own_user::OwnUser, register_user::RegisterUser,
+        authorize::Authorize,
+        create_room::CreateRoom,
+        idle_notice::{IdleNotice, Idleness},
+        join_rejected::{JoinRejected, Rejection},
+        join_room::JoinRoom,
+        leaderboard::Leaderboard,
+        leave_room::LeaveRoom,
+        name_rejected::{NameError, NameRejected},
+        own_user::OwnUser,
+        quick_play::QuickPlay,
+        request_leaderboard::RequestLeaderboard,
+        shutdown_notice::ShutdownNotice,
+        watch_room::WatchRoom,
+    },
+    messages::room::{
+        kick_player::KickPlayer, kicked::Kicked, move_seat::MoveSeat, set_ready::SetReady,
+        transfer_ownership::TransferOwnership,
     },
 };
-use naia_shared::Protocolize;
+use std::time::Duration;
+
+use naia_shared::{DefaultChannels, Protocolize, SharedConfig};
+
+/// naia ticks at the same rate on both ends. Clients start out at this one and take on the
+/// tick the server tells in `OwnUser`.
+pub const TICK_DEFAULT: Duration = Duration::from_millis(50);
 
 #[derive(Protocolize)]
 pub enum Protocol {
     Authorize(Authorize),
+    Bot(Bot),
+    Chat(Chat),
     CreateRoom(CreateRoom),
+    Guest(Guest),
+    IdleNotice(IdleNotice),
+    JoinRejected(JoinRejected),
     JoinRoom(JoinRoom),
+    KickPlayer(KickPlayer),
+    Kicked(Kicked),
+    Leaderboard(Leaderboard),
     LeaveRoom(LeaveRoom),
+    MoveSeat(MoveSeat),
     Name(Name),
+    NameRejected(NameRejected),
+    Online(Online),
     OwnUser(OwnUser),
     Owner(Owner),
     Player(Player),
-    RegisterUser(RegisterUser),
+    QuickPlay(QuickPlay),
+    Ready(Ready),
+    RequestLeaderboard(RequestLeaderboard),
     Room(Room),
+    RoomSummary(RoomSummary),
+    SendChat(SendChat),
+    SetReady(SetReady),
+    ShutdownNotice(ShutdownNotice),
+    Spectator(Spectator),
+    StartGame(StartGame),
+    TransferOwnership(TransferOwnership),
     User(User),
+    WatchRoom(WatchRoom),
+}
+
+pub fn shared_config(tick: Duration) -> SharedConfig<DefaultChannels> {
+    let mut config = SharedConfig::default();
+    config.tick_interval = Some(tick);
+    config
 }
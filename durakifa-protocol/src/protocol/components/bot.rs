@@ -0,0 +1,13 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::Replicate;
+
+/// Marks a player seated by the server to fill up a quick match. Bots carry their own name.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Bot;
+
+impl Bot {
+    pub fn new() -> Self {
+        Bot::new_complete()
+    }
+}
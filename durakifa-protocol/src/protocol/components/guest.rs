@@ -0,0 +1,12 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::Replicate;
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Guest;
+
+impl Guest {
+    pub fn new() -> Self {
+        Guest::new_complete()
+    }
+}
@@ -1,5 +1,11 @@
+pub mod bot;
+pub mod guest;
 pub mod name;
+pub mod online;
 pub mod owner;
 pub mod player;
+pub mod ready;
 pub mod room;
+pub mod room_summary;
+pub mod spectator;
 pub mod user;
@@ -0,0 +1,15 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+/// Lives on a single entity that only users in the lobby get, instead of every user entity.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Online {
+    pub users: Property<u16>,
+}
+
+impl Online {
+    pub fn new() -> Self {
+        Online::new_complete(0)
+    }
+}
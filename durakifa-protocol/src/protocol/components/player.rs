@@ -1,14 +1,17 @@
 use bevy_ecs::prelude::Component;
-use naia_shared::{EntityProperty, Replicate};
+use naia_shared::{EntityProperty, Property, Replicate};
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
 pub struct Player {
+    pub room: EntityProperty,
+    /// Index in the seating order of the room, which is also the order of play.
+    pub seat: Property<u8>,
     pub user: EntityProperty,
 }
 
 impl Player {
     pub fn new() -> Self {
-        Player::new_complete()
+        Player::new_complete(0)
     }
 }
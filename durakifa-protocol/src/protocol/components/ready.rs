@@ -0,0 +1,12 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::Replicate;
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Ready;
+
+impl Ready {
+    pub fn new() -> Self {
+        Ready::new_complete()
+    }
+}
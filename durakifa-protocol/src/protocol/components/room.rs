@@ -1,12 +1,34 @@
 use bevy_ecs::prelude::Component;
-use naia_shared::Replicate;
+use naia_shared::{derive_serde, serde, Property, Replicate};
+
+#[derive_serde]
+pub enum RoomStatus {
+    Finished,
+    InGame,
+    Waiting,
+}
+
+/// Rule set of a game of Durak.
+#[derive_serde]
+pub enum Variant {
+    /// Defenders may pass the attack on with a card of the same rank.
+    Perevodnoy,
+    /// Other players may throw in cards of ranks already on the table.
+    Podkidnoy,
+}
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
-pub struct Room;
+pub struct Room {
+    /// Invite code, joining by code also works for private rooms.
+    pub code: Property<String>,
+    /// Joining requires a password.
+    pub locked: Property<bool>,
+    pub status: Property<RoomStatus>,
+}
 
 impl Room {
-    pub fn new() -> Self {
-        Room::new_complete()
+    pub fn new(code: String, locked: bool) -> Self {
+        Room::new_complete(code, locked, RoomStatus::Waiting)
     }
 }
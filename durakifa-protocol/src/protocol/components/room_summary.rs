@@ -0,0 +1,20 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+use crate::protocol::Variant;
+
+/// What the lobby shows about a room next to the lock and status of its `Room`.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct RoomSummary {
+    pub max_players: Property<u8>,
+    /// Seated players, including bots.
+    pub players: Property<u8>,
+    pub variant: Property<Variant>,
+}
+
+impl RoomSummary {
+    pub fn new(max_players: u8, players: u8, variant: Variant) -> Self {
+        RoomSummary::new_complete(max_players, players, variant)
+    }
+}
@@ -0,0 +1,16 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{EntityProperty, Replicate};
+
+/// A user watching a room without a seat. Spectators only ever see the public table.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Spectator {
+    pub room: EntityProperty,
+    pub user: EntityProperty,
+}
+
+impl Spectator {
+    pub fn new() -> Self {
+        Spectator::new_complete()
+    }
+}
@@ -0,0 +1,17 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Chat {
+    pub name: Property<String>,
+    /// Only the room of the recipient got the message, not the whole lobby.
+    pub room: Property<bool>,
+    pub text: Property<String>,
+}
+
+impl Chat {
+    pub fn new(name: String, room: bool, text: String) -> Self {
+        Chat::new_complete(name, room, text)
+    }
+}
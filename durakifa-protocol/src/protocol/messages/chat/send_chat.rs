@@ -0,0 +1,18 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+/// Chat messages travel on `DefaultChannels::OrderedReliable`, so they arrive in order. No
+/// other message uses that channel, which keeps a lost chat packet from holding them up.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct SendChat {
+    /// Sends to the room the user is in instead of the whole lobby.
+    pub room: Property<bool>,
+    pub text: Property<String>,
+}
+
+impl SendChat {
+    pub fn new(room: bool, text: String) -> Self {
+        SendChat::new_complete(room, text)
+    }
+}
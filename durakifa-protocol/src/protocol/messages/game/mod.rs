@@ -0,0 +1 @@
+pub mod start_game;
@@ -0,0 +1,12 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::Replicate;
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct StartGame;
+
+impl StartGame {
+    pub fn new() -> Self {
+        StartGame::new_complete()
+    }
+}
@@ -1,14 +1,24 @@
 use bevy_ecs::prelude::Component;
 use naia_shared::{Property, Replicate};
 
+/// Logs in to the account `name`, or signs it up first if `sign_up` is set. Without a
+/// password the user plays as guest under `name`.
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
 pub struct Authorize {
-    pub key: Property<String>,
+    pub name: Property<String>,
+    pub password: Property<Option<String>>,
+    pub sign_up: Property<bool>,
+    pub token: Property<Option<String>>,
 }
 
 impl Authorize {
-    pub fn new(key: String) -> Self {
-        Authorize::new_complete(key)
+    pub fn new(
+        name: String,
+        password: Option<String>,
+        sign_up: bool,
+        token: Option<String>,
+    ) -> Self {
+        Authorize::new_complete(name, password, sign_up, token)
     }
 }
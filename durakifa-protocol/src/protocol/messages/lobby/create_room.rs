@@ -1,12 +1,19 @@
 use bevy_ecs::prelude::Component;
-use naia_shared::Replicate;
+use naia_shared::{Property, Replicate};
+
+use crate::protocol::Variant;
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
-pub struct CreateRoom;
+pub struct CreateRoom {
+    pub password: Property<Option<String>>,
+    /// Private rooms are not listed in the lobby and can only be joined by invite code.
+    pub private: Property<bool>,
+    pub variant: Property<Variant>,
+}
 
 impl CreateRoom {
-    pub fn new() -> Self {
-        CreateRoom::new_complete()
+    pub fn new(password: Option<String>, private: bool, variant: Variant) -> Self {
+        CreateRoom::new_complete(password, private, variant)
     }
 }
@@ -0,0 +1,27 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{derive_serde, serde, Property, Replicate};
+
+#[derive_serde]
+pub enum Idleness {
+    /// The room closed since nobody started a game in time.
+    Closed,
+    /// The user was disconnected from the lobby, its session is gone.
+    Disconnected,
+    /// The user was moved back to the lobby.
+    Moved,
+    /// The user gets moved to the lobby or disconnected after `seconds` without activity.
+    Warning,
+}
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct IdleNotice {
+    pub kind: Property<Idleness>,
+    pub seconds: Property<u16>,
+}
+
+impl IdleNotice {
+    pub fn new(kind: Idleness, seconds: u16) -> Self {
+        IdleNotice::new_complete(kind, seconds)
+    }
+}
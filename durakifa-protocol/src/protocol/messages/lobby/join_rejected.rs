@@ -0,0 +1,25 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{derive_serde, serde, Property, Replicate};
+
+#[derive_serde]
+pub enum Rejection {
+    Banned,
+    /// The server could not set up the room, the client may try again.
+    Failed,
+    Full,
+    InProgress,
+    NotFound,
+    Password,
+}
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct JoinRejected {
+    pub reason: Property<Rejection>,
+}
+
+impl JoinRejected {
+    pub fn new(reason: Rejection) -> Self {
+        JoinRejected::new_complete(reason)
+    }
+}
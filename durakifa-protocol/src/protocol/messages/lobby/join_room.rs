@@ -1,14 +1,21 @@
 use bevy_ecs::prelude::Component;
-use naia_shared::{EntityProperty, Replicate};
+use naia_shared::{EntityProperty, Property, Replicate};
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
 pub struct JoinRoom {
+    /// If set, the room is looked up by its invite code instead of `room`.
+    pub code: Property<Option<String>>,
+    pub password: Property<Option<String>>,
     pub room: EntityProperty,
 }
 
 impl JoinRoom {
-    pub fn new() -> Self {
-        JoinRoom::new_complete()
+    pub fn new(password: Option<String>) -> Self {
+        JoinRoom::new_complete(None, password)
+    }
+
+    pub fn by_code(code: String, password: Option<String>) -> Self {
+        JoinRoom::new_complete(Some(code), password)
     }
 }
@@ -0,0 +1,26 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+/// A page of the best rated players, `names` and `ratings` are parallel and start at
+/// position `first`. `rank` and `rating` belong to the receiving user and are unset for guests.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct Leaderboard {
+    pub first: Property<u8>,
+    pub names: Property<Vec<String>>,
+    pub rank: Property<Option<u32>>,
+    pub rating: Property<Option<u16>>,
+    pub ratings: Property<Vec<u16>>,
+}
+
+impl Leaderboard {
+    pub fn new(
+        first: u8,
+        names: Vec<String>,
+        rank: Option<u32>,
+        rating: Option<u16>,
+        ratings: Vec<u16>,
+    ) -> Self {
+        Leaderboard::new_complete(first, names, rank, rating, ratings)
+    }
+}
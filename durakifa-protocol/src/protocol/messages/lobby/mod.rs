@@ -1,6 +1,13 @@
 pub mod authorize;
 pub mod create_room;
+pub mod idle_notice;
+pub mod join_rejected;
 pub mod join_room;
+pub mod leaderboard;
 pub mod leave_room;
+pub mod name_rejected;
 pub mod own_user;
-pub mod register_user;
+pub mod quick_play;
+pub mod request_leaderboard;
+pub mod shutdown_notice;
+pub mod watch_room;
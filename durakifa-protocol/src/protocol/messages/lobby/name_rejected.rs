@@ -0,0 +1,25 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{derive_serde, serde, Property, Replicate};
+
+#[derive_serde]
+pub enum NameError {
+    /// Control characters and the like.
+    Characters,
+    Empty,
+    /// Another online user goes by the name.
+    Taken,
+    TooLong,
+}
+
+/// Tells a connecting user why its name was refused, no session is set up for it.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct NameRejected {
+    pub reason: Property<NameError>,
+}
+
+impl NameRejected {
+    pub fn new(reason: NameError) -> Self {
+        NameRejected::new_complete(reason)
+    }
+}
@@ -1,14 +1,18 @@
 use bevy_ecs::prelude::Component;
-use naia_shared::{EntityProperty, Replicate};
+use naia_shared::{EntityProperty, Property, Replicate};
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
 pub struct OwnUser {
+    pub room: EntityProperty,
+    /// Milliseconds between two server ticks.
+    pub tick: Property<u64>,
+    pub token: Property<String>,
     pub user: EntityProperty,
 }
 
 impl OwnUser {
-    pub fn new() -> Self {
-        OwnUser::new_complete()
+    pub fn new(tick: u64, token: String) -> Self {
+        OwnUser::new_complete(tick, token)
     }
 }
@@ -0,0 +1,18 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+use crate::protocol::Variant;
+
+/// Queues the user for a quick match, `None` accepts any setting. `LeaveRoom` leaves the queue.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct QuickPlay {
+    pub players: Property<Option<u8>>,
+    pub variant: Property<Option<Variant>>,
+}
+
+impl QuickPlay {
+    pub fn new(players: Option<u8>, variant: Option<Variant>) -> Self {
+        QuickPlay::new_complete(players, variant)
+    }
+}
@@ -0,0 +1,14 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct RequestLeaderboard {
+    pub count: Property<u8>,
+}
+
+impl RequestLeaderboard {
+    pub fn new(count: u8) -> Self {
+        RequestLeaderboard::new_complete(count)
+    }
+}
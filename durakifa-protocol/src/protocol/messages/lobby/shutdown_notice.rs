@@ -0,0 +1,16 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+/// Tells the users that the server goes down in `seconds`. Games in progress are restored
+/// once it is back.
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct ShutdownNotice {
+    pub seconds: Property<u16>,
+}
+
+impl ShutdownNotice {
+    pub fn new(seconds: u16) -> Self {
+        ShutdownNotice::new_complete(seconds)
+    }
+}
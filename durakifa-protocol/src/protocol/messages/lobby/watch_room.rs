@@ -0,0 +1,15 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{EntityProperty, Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct WatchRoom {
+    pub password: Property<Option<String>>,
+    pub room: EntityProperty,
+}
+
+impl WatchRoom {
+    pub fn new(password: Option<String>) -> Self {
+        WatchRoom::new_complete(password)
+    }
+}
@@ -1,2 +1,4 @@
+pub mod chat;
 pub mod game;
 pub mod lobby;
+pub mod room;
@@ -0,0 +1,16 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{EntityProperty, Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct KickPlayer {
+    /// Also keeps the user from joining the room again.
+    pub ban: Property<bool>,
+    pub player: EntityProperty,
+}
+
+impl KickPlayer {
+    pub fn new(ban: bool) -> Self {
+        KickPlayer::new_complete(ban)
+    }
+}
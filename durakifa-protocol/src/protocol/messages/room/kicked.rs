@@ -3,12 +3,12 @@ use naia_shared::{Property, Replicate};
 
 #[derive(Component, Replicate)]
 #[protocol_path = "crate::protocol::Protocol"]
-pub struct RegisterUser {
-    pub name: Property<String>,
+pub struct Kicked {
+    pub banned: Property<bool>,
 }
 
-impl RegisterUser {
-    pub fn new(name: String) -> Self {
-        RegisterUser::new_complete(name)
+impl Kicked {
+    pub fn new(banned: bool) -> Self {
+        Kicked::new_complete(banned)
     }
 }
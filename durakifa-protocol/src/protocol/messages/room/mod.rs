@@ -0,0 +1,5 @@
+pub mod kick_player;
+pub mod kicked;
+pub mod move_seat;
+pub mod set_ready;
+pub mod transfer_ownership;
@@ -0,0 +1,15 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{EntityProperty, Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct MoveSeat {
+    pub player: EntityProperty,
+    pub seat: Property<u8>,
+}
+
+impl MoveSeat {
+    pub fn new(seat: u8) -> Self {
+        MoveSeat::new_complete(seat)
+    }
+}
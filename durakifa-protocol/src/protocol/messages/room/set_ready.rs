@@ -0,0 +1,14 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{Property, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct SetReady {
+    pub ready: Property<bool>,
+}
+
+impl SetReady {
+    pub fn new(ready: bool) -> Self {
+        SetReady::new_complete(ready)
+    }
+}
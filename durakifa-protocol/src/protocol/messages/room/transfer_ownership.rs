@@ -0,0 +1,14 @@
+use bevy_ecs::prelude::Component;
+use naia_shared::{EntityProperty, Replicate};
+
+#[derive(Component, Replicate)]
+#[protocol_path = "crate::protocol::Protocol"]
+pub struct TransferOwnership {
+    pub player: EntityProperty,
+}
+
+impl TransferOwnership {
+    pub fn new() -> Self {
+        TransferOwnership::new_complete()
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy_ecs::system::Resource;
+use bevy_log::{error, info};
+
+use crate::logic::validation::CHAT_LEN_MAX;
+
+pub const HELP_TXT: &str = "\
+announce <text>      send a chat message to everyone online
+ban <name> [secs]    disconnect a user and ban its address
+close <code>         close a room and send its users back to the lobby
+dump                 log every naia room with its entities, as does SIGUSR1
+games                list the rooms with a game in progress
+help                 show this help
+inspect <code>       show the seats and spectators of a room
+kick <name>          disconnect a user and end its session
+log <filter>         set the log filter, e.g. debug or durakifa_server=trace,naia=warn
+replay <game>        show the moves of a recorded game
+rooms                list all rooms
+shutdown [secs]      warn everyone, save the games in progress and exit
+stats <name>         show the games, durak count, cards taken and win streak of a player
+users                list the users online";
+const PROMPT: &str = "> ";
+
+pub enum Command {
+    Announce(String),
+    /// Bans the address for the given time, or for the configured flood ban.
+    Ban(String, Option<Duration>),
+    Close(String),
+    Dump,
+    Games,
+    Help,
+    Inspect(String),
+    Kick(String),
+    Log(String),
+    Replay(u64),
+    Rooms,
+    /// Exits after the given time, or after the configured shutdown grace.
+    Shutdown(Option<Duration>),
+    Stats(String),
+    Users,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (word, rest) = match line.split_once(char::is_whitespace) {
+            Some((word, rest)) => (word, rest.trim()),
+            None => (line, ""),
+        };
+
+        let argument = |name: &str| match rest.is_empty() {
+            true => Err(format!("{} needs an argument, see help", name)),
+            false => Ok(rest.to_string()),
+        };
+
+        match word {
+            "announce" if rest.len() > CHAT_LEN_MAX => Err(format!(
+                "announce takes at most {} bytes of text",
+                CHAT_LEN_MAX
+            )),
+            "announce" => argument("announce").map(Command::Announce),
+            "ban" => {
+                let (name, secs) = match rest.rsplit_once(char::is_whitespace) {
+                    Some((name, secs)) if secs.parse::<u64>().is_ok() => {
+                        (name.trim(), secs.parse().ok())
+                    }
+                    _ => (rest, None),
+                };
+
+                match name.is_empty() {
+                    true => Err("ban needs a user name, see help".to_string()),
+                    false => Ok(Command::Ban(
+                        name.to_string(),
+                        secs.map(Duration::from_secs),
+                    )),
+                }
+            }
+            "close" => argument("close").map(Command::Close),
+            "dump" => Ok(Command::Dump),
+            "games" => Ok(Command::Games),
+            "help" => Ok(Command::Help),
+            "inspect" => argument("inspect").map(Command::Inspect),
+            "kick" => argument("kick").map(Command::Kick),
+            "log" => argument("log").map(Command::Log),
+            "replay" => match rest.parse() {
+                Ok(game) => Ok(Command::Replay(game)),
+                Err(_) => Err("replay needs a game id, see help".to_string()),
+            },
+            "rooms" => Ok(Command::Rooms),
+            "shutdown" => match rest {
+                "" => Ok(Command::Shutdown(None)),
+                secs => match secs.parse() {
+                    Ok(secs) => Ok(Command::Shutdown(Some(Duration::from_secs(secs)))),
+                    Err(_) => Err("shutdown takes a number of seconds".to_string()),
+                },
+            },
+            "stats" => argument("stats").map(Command::Stats),
+            "users" => Ok(Command::Users),
+            _ => Err(format!("unknown command {}, see help", word)),
+        }
+    }
+}
+
+pub struct Request {
+    pub line: String,
+    pub reply: Sender<String>,
+}
+
+/// Takes commands from stdin and, if configured, from a local Unix socket.
+#[derive(Resource)]
+pub struct Console {
+    dump: Arc<AtomicBool>,
+    requests: Mutex<Receiver<Request>>,
+}
+
+impl Console {
+    pub fn start(socket: Option<&Path>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stdin = sender.clone();
+        thread::spawn(move || {
+            print!("{}", PROMPT);
+            let _ = io::stdout().flush();
+            for line in io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+
+                match ask(&stdin, line) {
+                    Some(reply) => println!("{}", reply),
+                    None => break,
+                }
+
+                print!("{}", PROMPT);
+                let _ = io::stdout().flush();
+            }
+        });
+
+        if let Some(socket) = socket {
+            listen(socket, sender);
+        }
+
+        let dump = Arc::new(AtomicBool::new(false));
+        on_signal(&dump);
+        Console {
+            dump,
+            requests: Mutex::new(receiver),
+        }
+    }
+
+    pub fn request_dump(&self) {
+        self.dump.store(true, Ordering::Relaxed);
+    }
+
+    pub fn take_dump(&self) -> bool {
+        self.dump.swap(false, Ordering::Relaxed)
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        match self.requests.lock() {
+            Ok(requests) => requests.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Hands `line` to the server and waits for the reply. Returns `None` once the server is gone.
+fn ask(sender: &Sender<Request>, line: String) -> Option<String> {
+    let (reply, replies) = mpsc::channel();
+    sender.send(Request { line, reply }).ok()?;
+    replies.recv().ok()
+}
+
+#[cfg(unix)]
+fn listen(path: &Path, sender: Sender<Request>) {
+    use std::{io::BufReader, os::unix::net::UnixListener};
+
+    // A socket left behind by an earlier run would keep the bind from succeeding
+    let _ = std::fs::remove_file(path);
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("failed to bind admin socket {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    info!("admin socket listening on {}", path.display());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let mut writer = match stream.try_clone() {
+                    Ok(writer) => writer,
+                    Err(_) => return,
+                };
+
+                for line in BufReader::new(stream).lines() {
+                    let reply = match line.ok().and_then(|line| ask(&sender, line)) {
+                        Some(reply) => reply,
+                        None => break,
+                    };
+
+                    if writeln!(writer, "{}", reply).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn listen(path: &Path, _sender: Sender<Request>) {
+    error!(
+        "admin socket {} is only supported on Unix systems",
+        path.display()
+    );
+}
+
+#[cfg(unix)]
+fn on_signal(dump: &Arc<AtomicBool>) {
+    if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(dump)) {
+        error!("failed to register SIGUSR1: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn on_signal(_dump: &Arc<AtomicBool>) {}
@@ -0,0 +1,287 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bevy_ecs::system::Resource;
+use clap::Parser;
+use serde::Deserialize;
+
+const CFG_PATH: &str = "durakifa.toml";
+const DB_PATH: &str = "durakifa.sqlite";
+const SNAPSHOT_PATH: &str = "durakifa.snapshot.json";
+
+/// Command line flags, each of them can also be given as environment variable and takes
+/// precedence over the configuration file.
+#[derive(Parser)]
+#[command(about, version)]
+struct Args {
+    /// Address to bind to
+    #[arg(long, env = "DURAKIFA_ADDR")]
+    addr: Option<IpAddr>,
+    /// Path of a Unix socket that takes admin console commands
+    #[arg(long, env = "DURAKIFA_ADMIN_SOCKET")]
+    admin_socket: Option<PathBuf>,
+    /// Seconds a quick match waits for players before bots fill the missing seats
+    #[arg(long, env = "DURAKIFA_BOT_WAIT")]
+    bot_wait: Option<u64>,
+    /// Path of the configuration file [default: durakifa.toml, skipped if missing]
+    #[arg(long, env = "DURAKIFA_CONFIG")]
+    config: Option<PathBuf>,
+    /// Path of the SQLite database
+    #[arg(long, env = "DURAKIFA_DATABASE")]
+    database: Option<PathBuf>,
+    /// Seconds the address of a client that keeps flooding the server stays banned
+    #[arg(long, env = "DURAKIFA_FLOOD_BAN")]
+    flood_ban: Option<u64>,
+    /// Seconds without activity after which users are moved to the lobby, or disconnected
+    /// if they are in the lobby already
+    #[arg(long, env = "DURAKIFA_IDLE_TIMEOUT")]
+    idle_timeout: Option<u64>,
+    /// Seconds without activity after which users are warned
+    #[arg(long, env = "DURAKIFA_IDLE_WARN")]
+    idle_warn: Option<u64>,
+    /// Keep profiles and games in memory only, they are lost on restart
+    #[arg(long, env = "DURAKIFA_IN_MEMORY")]
+    in_memory: bool,
+    /// Log one JSON object per line instead of plain text
+    #[arg(long, env = "DURAKIFA_LOG_JSON")]
+    log_json: bool,
+    /// Log filter, e.g. info or durakifa_server=debug,naia=warn. RUST_LOG takes precedence
+    #[arg(long, env = "DURAKIFA_LOG_LEVEL")]
+    log_level: Option<String>,
+    /// Password logins an address may start per minute before it is banned. Everyone behind
+    /// a shared NAT counts against the same address
+    #[arg(long, env = "DURAKIFA_LOGIN_ATTEMPTS")]
+    login_attempts: Option<u32>,
+    /// Address of an HTTP endpoint serving metrics to Prometheus, e.g. 127.0.0.1:9100
+    #[arg(long, env = "DURAKIFA_METRICS_ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    /// Port for the session (signaling) connection
+    #[arg(long, env = "DURAKIFA_PORT")]
+    port: Option<u16>,
+    /// Port for the WebRTC data channel
+    #[arg(long, env = "DURAKIFA_PORT_WRTC")]
+    port_wrtc: Option<u16>,
+    /// Seconds a disconnected user may take to resume its session
+    #[arg(long, env = "DURAKIFA_RESUME_GRACE")]
+    resume_grace: Option<u64>,
+    /// Seconds without activity after which a room that never started a game is closed
+    #[arg(long, env = "DURAKIFA_ROOM_TIMEOUT")]
+    room_timeout: Option<u64>,
+    /// Seconds between the shutdown notice and the exit
+    #[arg(long, env = "DURAKIFA_SHUTDOWN_GRACE")]
+    shutdown_grace: Option<u64>,
+    /// Path of the file games in progress are saved to on shutdown and restored from. It holds
+    /// session tokens, so only the server user may read it
+    #[arg(long, env = "DURAKIFA_SNAPSHOT")]
+    snapshot: Option<PathBuf>,
+    /// Milliseconds between two server ticks
+    #[arg(long, env = "DURAKIFA_TICK")]
+    tick: Option<u64>,
+    /// Public URL of the WebRTC endpoint, e.g. https://durakifa.example.com:55501
+    #[arg(long, env = "DURAKIFA_URL_PUB")]
+    url_pub: Option<String>,
+}
+
+#[derive(Deserialize, Resource)]
+#[serde(default)]
+pub struct Config {
+    pub addr: IpAddr,
+    pub admin_socket: Option<PathBuf>,
+    pub bot_wait: u64,
+    pub database: Option<PathBuf>,
+    pub flood_ban: u64,
+    pub idle_timeout: u64,
+    pub idle_warn: u64,
+    pub log_json: bool,
+    pub log_level: String,
+    pub login_attempts: u32,
+    pub metrics_addr: Option<SocketAddr>,
+    pub port: u16,
+    pub port_wrtc: u16,
+    pub resume_grace: u64,
+    pub room_timeout: u64,
+    pub shutdown_grace: u64,
+    pub snapshot: Option<PathBuf>,
+    pub tick: u64,
+    pub url_pub: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            admin_socket: None,
+            bot_wait: 30,
+            database: Some(PathBuf::from(DB_PATH)),
+            flood_ban: 600,
+            idle_timeout: 300,
+            idle_warn: 240,
+            log_json: false,
+            log_level: String::from("info"),
+            login_attempts: 60,
+            metrics_addr: None,
+            port: 55500,
+            port_wrtc: 55501,
+            resume_grace: 60,
+            room_timeout: 900,
+            shutdown_grace: 30,
+            snapshot: Some(PathBuf::from(SNAPSHOT_PATH)),
+            tick: 50,
+            url_pub: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration file and applies environment variables and command line flags
+    /// on top of it.
+    pub fn load() -> Self {
+        let args = Args::parse();
+        // Only the default file may be missing, a path that was asked for has to be there
+        let cfg = match &args.config {
+            Some(path) => fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", path.display(), e)),
+            None => match fs::read_to_string(CFG_PATH) {
+                Ok(cfg) => cfg,
+                Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+                Err(e) => panic!("failed to read {}: {}", CFG_PATH, e),
+            },
+        };
+        let mut config: Config = toml::from_str(&cfg).expect("invalid configuration file");
+
+        if let Some(addr) = args.addr {
+            config.addr = addr;
+        }
+
+        if args.admin_socket.is_some() {
+            config.admin_socket = args.admin_socket;
+        }
+
+        if let Some(bot_wait) = args.bot_wait {
+            config.bot_wait = bot_wait;
+        }
+
+        if args.database.is_some() {
+            config.database = args.database;
+        }
+
+        if let Some(flood_ban) = args.flood_ban {
+            config.flood_ban = flood_ban;
+        }
+
+        if let Some(idle_timeout) = args.idle_timeout {
+            config.idle_timeout = idle_timeout;
+        }
+
+        if let Some(idle_warn) = args.idle_warn {
+            config.idle_warn = idle_warn;
+        }
+
+        if args.in_memory {
+            config.database = None;
+            config.snapshot = None;
+        }
+
+        if args.log_json {
+            config.log_json = true;
+        }
+
+        if let Some(log_level) = args.log_level {
+            config.log_level = log_level;
+        }
+
+        if let Some(login_attempts) = args.login_attempts {
+            config.login_attempts = login_attempts;
+        }
+
+        if args.metrics_addr.is_some() {
+            config.metrics_addr = args.metrics_addr;
+        }
+
+        if let Some(port) = args.port {
+            config.port = port;
+        }
+
+        if let Some(port_wrtc) = args.port_wrtc {
+            config.port_wrtc = port_wrtc;
+        }
+
+        if let Some(resume_grace) = args.resume_grace {
+            config.resume_grace = resume_grace;
+        }
+
+        if let Some(room_timeout) = args.room_timeout {
+            config.room_timeout = room_timeout;
+        }
+
+        if let Some(shutdown_grace) = args.shutdown_grace {
+            config.shutdown_grace = shutdown_grace;
+        }
+
+        if args.snapshot.is_some() {
+            config.snapshot = args.snapshot;
+        }
+
+        if let Some(tick) = args.tick {
+            config.tick = tick;
+        }
+
+        if args.url_pub.is_some() {
+            config.url_pub = args.url_pub;
+        }
+
+        config
+    }
+
+    pub fn addr_session(&self) -> SocketAddr {
+        SocketAddr::new(self.addr, self.port)
+    }
+
+    pub fn addr_wrtc(&self) -> SocketAddr {
+        SocketAddr::new(self.addr, self.port_wrtc)
+    }
+
+    pub fn bot_wait(&self) -> Duration {
+        Duration::from_secs(self.bot_wait)
+    }
+
+    pub fn flood_ban(&self) -> Duration {
+        Duration::from_secs(self.flood_ban)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout)
+    }
+
+    pub fn idle_warn(&self) -> Duration {
+        Duration::from_secs(self.idle_warn)
+    }
+
+    pub fn resume_grace(&self) -> Duration {
+        Duration::from_secs(self.resume_grace)
+    }
+
+    pub fn room_timeout(&self) -> Duration {
+        Duration::from_secs(self.room_timeout)
+    }
+
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace)
+    }
+
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick)
+    }
+
+    pub fn url_pub(&self) -> String {
+        match &self.url_pub {
+            Some(url) => url.clone(),
+            None => format!("http://{}", self.addr_wrtc()),
+        }
+    }
+}
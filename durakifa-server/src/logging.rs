@@ -0,0 +1,29 @@
+use bevy_ecs::system::Resource;
+use tracing_subscriber::{
+    fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// Lets the admin console change the log filter of the running server.
+#[derive(Resource)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogFilter {
+    pub fn init(filter: &str, json: bool) -> Self {
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
+        let (filter, handle) = reload::Layer::new(filter);
+        let registry = tracing_subscriber::registry().with(filter);
+        match json {
+            true => registry.with(fmt::layer().json()).init(),
+            false => registry.with(fmt::layer()).init(),
+        }
+
+        LogFilter { handle }
+    }
+
+    pub fn set(&self, filter: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(filter).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use crate::storage::{Profile, Storage};
+
+use super::rating::RATING_INIT;
+
+/// Account names are unique regardless of their case.
+pub fn exists(storage: &dyn Storage, name: &str) -> bool {
+    storage.profile(name).is_some()
+}
+
+/// Creates an account with a password `hash`, returns `false` if the name is taken.
+pub fn sign_up(storage: &mut dyn Storage, name: &str, hash: String) -> bool {
+    !exists(storage, name)
+        && storage.insert_profile(Profile {
+            name: name.to_string(),
+            password: hash,
+            rating: RATING_INIT,
+        })
+}
+
+enum Job {
+    Hash(String),
+    Verify { hash: String, password: String },
+}
+
+pub enum Outcome {
+    Hashed(Option<String>),
+    Verified(bool),
+}
+
+/// Runs Argon2 on a thread of its own, as one hash takes longer than a tick. `T` tells apart
+/// whose outcome it is.
+pub struct Hasher<T> {
+    jobs: Sender<(T, Job)>,
+    outcomes: Mutex<Receiver<(T, Outcome)>>,
+}
+
+impl<T: Send + 'static> Hasher<T> {
+    pub fn start() -> Self {
+        let (jobs, queue) = mpsc::channel::<(T, Job)>();
+        let (sender, outcomes) = mpsc::channel();
+        thread::spawn(move || {
+            for (tag, job) in queue {
+                let outcome = match job {
+                    Job::Hash(password) => Outcome::Hashed(hash(&password)),
+                    Job::Verify { hash, password } => Outcome::Verified(matches(&hash, &password)),
+                };
+
+                if sender.send((tag, outcome)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Hasher {
+            jobs,
+            outcomes: Mutex::new(outcomes),
+        }
+    }
+
+    pub fn hash(&self, tag: T, password: String) {
+        let _ = self.jobs.send((tag, Job::Hash(password)));
+    }
+
+    pub fn outcomes(&self) -> Vec<(T, Outcome)> {
+        match self.outcomes.lock() {
+            Ok(outcomes) => outcomes.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn verify(&self, tag: T, hash: String, password: String) {
+        let _ = self.jobs.send((tag, Job::Verify { hash, password }));
+    }
+}
+
+/// Salts and hashes `password` into a string that holds everything `matches` needs.
+pub fn hash(password: &str) -> Option<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .ok()
+        .map(|hash| hash.to_string())
+}
+
+fn matches(hash: &str, password: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
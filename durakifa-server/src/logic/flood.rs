@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use bevy_log::warn;
+use durakifa_protocol::protocol::Protocol;
+use naia_bevy_server::{shared::BigMapKey, UserKey};
+
+/// Dropped messages within `STRIKE_WINDOW` that get a user disconnected.
+const STRIKES_MAX: u32 = 20;
+const STRIKE_WINDOW: Duration = Duration::from_secs(10);
+
+struct Bucket {
+    tokens: f32,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(rate: &Rate, now: Instant) -> Self {
+        Bucket {
+            tokens: rate.burst,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: &Rate, now: Instant) {
+        let refill = now.duration_since(self.updated).as_secs_f32() * rate.per_sec;
+        self.tokens = (self.tokens + refill).min(rate.burst);
+        self.updated = now;
+    }
+
+    fn take(&mut self, rate: &Rate, now: Instant) -> bool {
+        self.refill(rate, now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+struct Rate {
+    burst: f32,
+    per_sec: f32,
+}
+
+struct Strikes {
+    count: u32,
+    since: Instant,
+}
+
+pub struct Limiter {
+    /// Password logins per address, as each costs an Argon2 hash.
+    attempt_rate: Rate,
+    attempts: HashMap<IpAddr, Bucket>,
+    bans: HashMap<IpAddr, Instant>,
+    buckets: HashMap<(UserKey, &'static str), Bucket>,
+    dropped: HashMap<&'static str, u64>,
+    offenders: Vec<UserKey>,
+    strikes: HashMap<UserKey, Strikes>,
+}
+
+impl Limiter {
+    /// Lets an address start `logins` password logins a minute, all of them at once if it
+    /// was quiet before.
+    pub fn new(logins: u32) -> Self {
+        Limiter {
+            attempt_rate: Rate {
+                burst: logins as f32,
+                per_sec: logins as f32 / 60.0,
+            },
+            attempts: HashMap::new(),
+            bans: HashMap::new(),
+            buckets: HashMap::new(),
+            dropped: HashMap::new(),
+            offenders: Vec::new(),
+            strikes: HashMap::new(),
+        }
+    }
+
+    pub fn admit(&mut self, user_key: UserKey, msg: &Protocol) -> bool {
+        let (kind, rate) = kind(msg);
+        let now = Instant::now();
+        let bucket = self
+            .buckets
+            .entry((user_key, kind))
+            .or_insert_with(|| Bucket::full(&rate, now));
+
+        if bucket.take(&rate, now) {
+            return true;
+        }
+
+        warn!(
+            user = user_key.to_u64(),
+            kind, "message dropped, rate limit exceeded"
+        );
+        *self.dropped.entry(kind).or_insert(0) += 1;
+        let strikes = self.strikes.entry(user_key).or_insert(Strikes {
+            count: 0,
+            since: now,
+        });
+
+        if now.duration_since(strikes.since) > STRIKE_WINDOW {
+            strikes.count = 0;
+            strikes.since = now;
+        }
+
+        strikes.count += 1;
+        if strikes.count == STRIKES_MAX {
+            self.offenders.push(user_key);
+        }
+
+        false
+    }
+
+    /// Counts a password login from `addr`, which gets banned for `duration` once it
+    /// makes too many.
+    pub fn attempt(&mut self, addr: IpAddr, duration: Duration) -> bool {
+        let now = Instant::now();
+        // Buckets that filled up again tell nothing a new one would not
+        self.attempts.retain(|_, bucket| {
+            bucket.refill(&self.attempt_rate, now);
+            bucket.tokens < self.attempt_rate.burst
+        });
+
+        let bucket = self
+            .attempts
+            .entry(addr)
+            .or_insert_with(|| Bucket::full(&self.attempt_rate, now));
+
+        if bucket.take(&self.attempt_rate, now) {
+            return true;
+        }
+
+        warn!(%addr, "address banned, too many login attempts");
+        self.attempts.remove(&addr);
+        self.ban(addr, duration);
+        false
+    }
+
+    pub fn ban(&mut self, addr: IpAddr, duration: Duration) {
+        self.bans.insert(addr, Instant::now() + duration);
+    }
+
+    pub fn dropped(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        self.dropped.iter().map(|(&kind, &count)| (kind, count))
+    }
+
+    pub fn forget(&mut self, user_key: UserKey) {
+        self.buckets.retain(|(key, _), _| *key != user_key);
+        self.strikes.remove(&user_key);
+    }
+
+    pub fn is_banned(&mut self, addr: IpAddr) -> bool {
+        let now = Instant::now();
+        self.bans.retain(|_, until| *until > now);
+        self.bans.contains_key(&addr)
+    }
+
+    pub fn offenders(&mut self) -> Vec<UserKey> {
+        std::mem::take(&mut self.offenders)
+    }
+}
+
+pub fn message_kind(msg: &Protocol) -> &'static str {
+    kind(msg).0
+}
+
+/// Every room spawns naia rooms and entities, so creating them is limited the most.
+fn kind(msg: &Protocol) -> (&'static str, Rate) {
+    let (kind, burst, per_sec) = match msg {
+        Protocol::CreateRoom(_) => ("CreateRoom", 2.0, 0.2),
+        Protocol::JoinRoom(_) => ("JoinRoom", 5.0, 1.0),
+        Protocol::KickPlayer(_) => ("KickPlayer", 5.0, 1.0),
+        Protocol::LeaveRoom(_) => ("LeaveRoom", 5.0, 1.0),
+        Protocol::MoveSeat(_) => ("MoveSeat", 10.0, 4.0),
+        Protocol::QuickPlay(_) => ("QuickPlay", 10.0, 4.0),
+        Protocol::RequestLeaderboard(_) => ("RequestLeaderboard", 3.0, 0.5),
+        Protocol::SendChat(_) => ("SendChat", 5.0, 1.0),
+        Protocol::SetReady(_) => ("SetReady", 10.0, 4.0),
+        Protocol::StartGame(_) => ("StartGame", 5.0, 1.0),
+        Protocol::TransferOwnership(_) => ("TransferOwnership", 5.0, 1.0),
+        Protocol::WatchRoom(_) => ("WatchRoom", 5.0, 1.0),
+        _ => ("other", 10.0, 4.0),
+    };
+
+    (kind, Rate { burst, per_sec })
+}
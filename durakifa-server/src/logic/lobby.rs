@@ -1,145 +1,1648 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
 
 use bevy_ecs::prelude::Entity;
-use durakifa_protocol::protocol::Protocol;
-use naia_bevy_server::{shared::DefaultChannels, RoomKey, Server, UserKey};
+use durakifa_protocol::protocol::{Rejection, RoomStatus, Variant};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
 
-struct LobbyRoom {
-    entity: Entity,
-    players: HashMap<UserKey, Entity>,
+use super::rules::Rules;
+use crate::storage::{GameResult, Placement};
+
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 6;
+const QUICK_PLAYERS: usize = 4;
+const TOKEN_LEN: usize = 32;
+
+/// The network side of the lobby, which keeps `Lobby` testable without a server.
+pub trait Host<K> {
+    fn create_room(&mut self, private: bool) -> Entity;
+    fn despawn(&mut self, entity: Entity);
+    fn destroy_room(&mut self, room: Entity);
+    fn enter_lobby(&mut self, user_key: K);
+    fn enter_room(&mut self, user_key: K, room: Entity);
+    fn leave_room(&mut self, user_key: K, room: Entity);
+    fn spawn_member(&mut self, room: Entity) -> Entity;
+    fn spawn_user(&mut self) -> Entity;
+}
+
+struct Activity {
+    since: Instant,
+    warned: bool,
+}
+
+/// Who a room ban applies to. Names can be changed at will, so registered users are banned by
+/// their account and guests by their address.
+#[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Identity {
+    Account(String),
+    Address(IpAddr),
+}
+
+pub enum Idle {
+    Disconnect,
+    Moved,
+    Warning(Duration),
+}
+
+struct LobbyRoom<K> {
+    active: Instant,
+    banned: HashSet<Identity>,
+    code: String,
+    /// Players that walked out of the game in progress, first one first.
+    forfeits: Vec<String>,
+    owner: K,
+    /// Hashed by `accounts::hash`, the snapshot carries it as it is.
+    password: Option<String>,
+    private: bool,
+    rules: Rules,
+    /// Players in seating order.
+    seats: Vec<Seat<K>>,
+    spectators: HashMap<K, Entity>,
+    started: bool,
+    status: RoomStatus,
+}
+
+impl<K: Copy + Eq + Hash> LobbyRoom<K> {
+    /// A game without enough players left is over. Whoever walked out first is the durak,
+    /// the players still seated come out ahead of everyone who left.
+    fn abandon(&mut self) -> Option<GameResult> {
+        // Bots keep their seats, so only the users that are left count
+        let users = self
+            .seats
+            .iter()
+            .filter(|seat| seat.user_key.is_some())
+            .count();
+        if self.status != RoomStatus::InGame || users >= self.rules.min_players() {
+            return None;
+        }
+
+        self.status = RoomStatus::Finished;
+        let forfeits = std::mem::take(&mut self.forfeits);
+        if forfeits.is_empty() {
+            return None;
+        }
+
+        let mut players = self
+            .seats
+            .iter()
+            .filter(|seat| seat.user_key.is_some())
+            .map(|seat| seat.name.clone())
+            .chain(forfeits.into_iter().rev())
+            .map(|name| Placement {
+                cards_taken: None,
+                durak: false,
+                name,
+            })
+            .collect::<Vec<_>>();
+
+        // Bots are no players of their own, a user alone with them lost to nobody
+        if players.len() < 2 {
+            return None;
+        }
+
+        if let Some(durak) = players.last_mut() {
+            durak.durak = true;
+        }
+
+        // Nobody played the game out, so there are no moves to replay
+        Some(GameResult {
+            players,
+            replay: Vec::new(),
+        })
+    }
+
+    /// `unlocked` tells that the hasher found the given password right.
+    fn admit(&self, identity: Option<&Identity>, unlocked: bool) -> Result<(), Rejection> {
+        if matches!(identity, Some(identity) if self.banned.contains(identity)) {
+            return Err(Rejection::Banned);
+        }
+
+        if self.password.is_some() && !unlocked {
+            return Err(Rejection::Password);
+        }
+
+        Ok(())
+    }
+
+    fn contains(&self, user_key: K) -> bool {
+        self.seats
+            .iter()
+            .any(|seat| seat.user_key == Some(user_key))
+    }
+
+    fn player(&self, user_key: K) -> Option<Entity> {
+        self.seats
+            .iter()
+            .find(|seat| seat.user_key == Some(user_key))
+            .map(|seat| seat.player)
+    }
+
+    /// The longest present player takes over if the owner left.
+    fn remove(&mut self, user_key: K) -> Option<Entity> {
+        let index = self
+            .seats
+            .iter()
+            .position(|seat| seat.user_key == Some(user_key))?;
+        let seat = self.seats.remove(index);
+        if self.status == RoomStatus::InGame {
+            self.forfeits.push(seat.name);
+        }
+
+        self.unready();
+
+        // Bots never own a room, it closes once only bots are left
+        if self.owner == user_key {
+            if let Some(successor) = self
+                .seats
+                .iter()
+                .filter_map(|seat| seat.user_key.map(|key| (seat.joined, key)))
+                .min_by_key(|(joined, _)| *joined)
+            {
+                self.owner = successor.1;
+            }
+        }
+
+        Some(seat.player)
+    }
+
+    /// Everyone has to confirm again once the seating or the rules have changed. Bots are
+    /// always ready.
+    fn unready(&mut self) {
+        for seat in self.seats.iter_mut() {
+            seat.ready = seat.user_key.is_none();
+        }
+    }
+}
+
+pub struct Match<K> {
+    pub bots: Vec<Entity>,
+    pub code: String,
+    pub players: Vec<(Entity, K)>,
+    pub room: Entity,
+}
+
+/// A user waiting for a quick match, `None` accepts any setting.
+struct Queued<K> {
+    name: String,
+    players: Option<usize>,
+    since: Instant,
+    user_key: K,
+    variant: Option<Variant>,
 }
 
-pub struct Lobby {
-    lobby_key: RoomKey,
-    users: HashMap<UserKey, Entity>,
-    rooms: HashMap<RoomKey, LobbyRoom>,
+impl<K> Queued<K> {
+    fn accepts(&self, len: usize, players: Option<usize>, variant: &Option<Variant>) -> bool {
+        let players = match (self.players, players) {
+            (Some(own), _) if own <= len => false,
+            (Some(own), Some(other)) => own == other,
+            _ => true,
+        };
+
+        players
+            && match (&self.variant, variant) {
+                (Some(own), Some(other)) => own == other,
+                _ => true,
+            }
+    }
+}
+
+pub struct Reclaim {
+    pub bots: Vec<Entity>,
+    /// Invite code of the room if the user is the first one back, which spawns the room.
+    pub code: Option<String>,
+    pub locked: bool,
+    pub player: Entity,
+    pub room: Entity,
+}
+
+/// A game from before a restart, waiting for its players to come back.
+struct Saved {
+    /// Room entity, once the first player is back.
+    entity: Option<Entity>,
+    room: SavedRoom,
+    since: Instant,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SavedRoom {
+    banned: Vec<Identity>,
+    code: String,
+    password: Option<String>,
+    private: bool,
+    seats: Vec<SavedSeat>,
+    variant: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct SavedSeat {
+    name: Option<String>,
+    owner: bool,
+    /// Session token of the player, `None` once it is back.
+    token: Option<String>,
+}
+
+struct Seat<K> {
+    joined: Instant,
+    name: String,
+    player: Entity,
+    ready: bool,
+    user_key: Option<K>,
+}
+
+pub struct Summary<'a> {
+    pub max_players: usize,
+    pub owner: Option<Entity>,
+    pub players: usize,
+    pub room: Entity,
+    pub status: &'a RoomStatus,
+    pub variant: &'a Variant,
 }
 
-impl Lobby {
-    pub fn new(lobby_key: RoomKey) -> Self {
+pub struct Lobby<K> {
+    activity: HashMap<K, Activity>,
+    finished: Vec<GameResult>,
+    identities: HashMap<K, Identity>,
+    /// Room entity each user sits in or watches, a user is in one room at most.
+    memberships: HashMap<K, Entity>,
+    orphans: HashMap<K, Instant>,
+    /// Users waiting for a quick match, longest waiting first.
+    queue: Vec<Queued<K>>,
+    rooms: HashMap<Entity, LobbyRoom<K>>,
+    saved: Vec<Saved>,
+    tokens: HashMap<String, K>,
+    users: HashMap<K, Entity>,
+}
+
+impl<K: Copy + Eq + Hash> Lobby<K> {
+    pub fn new() -> Self {
         Lobby {
-            lobby_key,
-            users: HashMap::new(),
+            activity: HashMap::new(),
+            finished: Vec::new(),
+            identities: HashMap::new(),
+            memberships: HashMap::new(),
+            orphans: HashMap::new(),
+            queue: Vec::new(),
             rooms: HashMap::new(),
+            saved: Vec::new(),
+            tokens: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    pub fn ban(&mut self, user_key: K, target: K) {
+        let identity = match self.identities.get(&target) {
+            Some(identity) => identity.clone(),
+            None => return,
+        };
+
+        if let Some(room) = self.seated_mut(user_key) {
+            room.banned.insert(identity);
         }
     }
 
-    pub fn clear_user<'world, 'state>(
+    pub fn clear_user(&mut self, host: &mut impl Host<K>, user_key: K) {
+        self.leave_room(host, user_key);
+        self.activity.remove(&user_key);
+        self.identities.remove(&user_key);
+        self.orphans.remove(&user_key);
+        self.tokens.retain(|_, key| *key != user_key);
+        if let Some(user) = self.users.remove(&user_key) {
+            host.despawn(user);
+        }
+    }
+
+    pub fn close_room(&mut self, host: &mut impl Host<K>, room: Entity) -> Vec<K> {
+        let members = match self.rooms.get(&room) {
+            Some(room) => room
+                .seats
+                .iter()
+                .filter_map(|seat| seat.user_key)
+                .chain(room.spectators.keys().copied())
+                .collect::<Vec<_>>(),
+            None => return Vec::new(),
+        };
+
+        for &user_key in members.iter() {
+            self.leave_room(host, user_key);
+        }
+
+        members
+    }
+
+    pub fn close_stale(&mut self, host: &mut impl Host<K>, after: Duration) -> Vec<K> {
+        let stale = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| !room.started && room.active.elapsed() >= after)
+            .map(|(&entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        stale
+            .into_iter()
+            .flat_map(|room| self.close_room(host, room))
+            .collect()
+    }
+
+    pub fn code_room(&self, code: &str) -> Option<Entity> {
+        let code = code.trim().to_uppercase();
+        self.rooms
+            .iter()
+            .find(|(_, room)| room.code == code)
+            .map(|(&entity, _)| entity)
+    }
+
+    pub fn enter_room(
         &mut self,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-        user_key: UserKey,
-    ) -> Option<(Entity, Entity)> {
-        let mut res = None;
-        for (room_key, room) in self.rooms.iter_mut() {
-            if let Some(player) = room.players.remove(&user_key) {
-                server.entity_mut(&player).leave_room(room_key).despawn();
-                if let Some(&successor) = room.players.values().next() {
-                    res = Some((room.entity, successor));
+        host: &mut impl Host<K>,
+        room: Entity,
+        user_key: K,
+        name: &str,
+        unlocked: bool,
+    ) -> Result<Entity, Rejection> {
+        let lobby_room = self.rooms.get(&room).ok_or(Rejection::NotFound)?;
+        if let Some(player) = lobby_room.player(user_key) {
+            return Ok(player);
+        }
+
+        lobby_room.admit(self.identities.get(&user_key), unlocked)?;
+        if lobby_room.status == RoomStatus::InGame {
+            return Err(Rejection::InProgress);
+        }
+
+        if lobby_room.seats.len() >= lobby_room.rules.max_players() {
+            return Err(Rejection::Full);
+        }
+
+        self.leave_room(host, user_key);
+        self.sit(host, room, user_key, name)
+            .ok_or(Rejection::NotFound)
+    }
+
+    pub fn expired(&self, grace: Duration) -> Vec<K> {
+        self.orphans
+            .iter()
+            .filter(|(_, since)| since.elapsed() > grace)
+            .map(|(&user_key, _)| user_key)
+            .collect()
+    }
+
+    pub fn fellow(&self, user_key: K, player: Entity) -> Option<K> {
+        self.seated(user_key)?
+            .seats
+            .iter()
+            .find(|seat| seat.player == player && seat.user_key != Some(user_key))
+            .and_then(|seat| seat.user_key)
+    }
+
+    pub fn forget_saved(&mut self, grace: Duration) {
+        // A game is settled once all of its players are back
+        self.saved
+            .retain(|saved| saved.room.seats.iter().any(|seat| seat.token.is_some()));
+        let (expired, saved) = std::mem::take(&mut self.saved)
+            .into_iter()
+            .partition::<Vec<_>, _>(|saved| saved.since.elapsed() > grace);
+        self.saved = saved;
+        for saved in expired {
+            let lobby_room = match saved.entity.and_then(|room| self.rooms.get_mut(&room)) {
+                Some(lobby_room) => lobby_room,
+                None => continue,
+            };
+
+            // Players that never came back walked out
+            lobby_room.forfeits.extend(
+                saved
+                    .room
+                    .seats
+                    .into_iter()
+                    .filter(|seat| seat.token.is_some())
+                    .filter_map(|seat| seat.name),
+            );
+            self.finished.extend(lobby_room.abandon());
+        }
+    }
+
+    pub fn get_user(&self, user_key: K) -> Option<Entity> {
+        self.users.get(&user_key).copied()
+    }
+
+    /// Whether restored games still wait for some of their players.
+    pub fn has_saved(&self) -> bool {
+        !self.saved.is_empty()
+    }
+
+    /// Users idle in a room are moved back to the lobby and start over. Players of a game in
+    /// progress wait for their turn, their time only starts once the game is over.
+    pub fn idle(
+        &mut self,
+        host: &mut impl Host<K>,
+        warn: Duration,
+        timeout: Duration,
+    ) -> Vec<(K, Idle)> {
+        let playing = self
+            .activity
+            .keys()
+            .copied()
+            .filter(|&user_key| {
+                self.seated(user_key)
+                    .is_some_and(|room| room.status == RoomStatus::InGame)
+            })
+            .collect::<HashSet<_>>();
+
+        let mut idle = Vec::new();
+        for (&user_key, activity) in self.activity.iter_mut() {
+            if playing.contains(&user_key) {
+                activity.since = Instant::now();
+                activity.warned = false;
+                continue;
+            }
+
+            if self.orphans.contains_key(&user_key) {
+                continue;
+            }
+
+            let elapsed = activity.since.elapsed();
+            if elapsed >= timeout {
+                match self.memberships.contains_key(&user_key) {
+                    true => idle.push((user_key, Idle::Moved)),
+                    false => idle.push((user_key, Idle::Disconnect)),
                 }
+            } else if elapsed >= warn && !activity.warned {
+                activity.warned = true;
+                idle.push((user_key, Idle::Warning(timeout - elapsed)));
             }
         }
 
-        self.tidy(server);
-        if let Some(user) = self.users.remove(&user_key) {
-            server
-                .entity_mut(&user)
-                .leave_room(&self.lobby_key)
-                .despawn();
+        for (user_key, _) in idle.iter().filter(|(_, idle)| matches!(idle, Idle::Moved)) {
+            self.leave_room(host, *user_key);
+            self.touch(*user_key);
         }
 
-        res
+        idle
+    }
+
+    pub fn is_connected(&self, token: &str) -> bool {
+        match self.tokens.get(token) {
+            Some(user_key) => !self.orphans.contains_key(user_key),
+            None => false,
+        }
+    }
+
+    pub fn is_owner(&self, user_key: K) -> bool {
+        match self.seated(user_key) {
+            Some(room) => room.owner == user_key,
+            None => false,
+        }
     }
 
-    pub fn enter_room<'world, 'state>(
+    /// Sends a fellow player of the owner `user_key` back to the lobby, though not during a game,
+    /// which the player would lose by leaving.
+    pub fn kick(
         &mut self,
-        room: Entity,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-        user_key: UserKey,
-    ) -> Option<Entity> {
-        for (room_key, lobby_room) in self.rooms.iter_mut() {
-            if lobby_room.entity == room {
-                let player = server.spawn().enter_room(room_key).id();
-                lobby_room.players.insert(user_key, player);
-                server.user_mut(&user_key).enter_room(room_key);
-                return Some(player);
+        host: &mut impl Host<K>,
+        user_key: K,
+        player: Entity,
+        ban: bool,
+    ) -> Result<K, Rejection> {
+        if !self.is_owner(user_key) {
+            return Err(Rejection::NotFound);
+        }
+
+        let target = self.fellow(user_key, player).ok_or(Rejection::NotFound)?;
+        if self
+            .seated(user_key)
+            .is_some_and(|room| room.status == RoomStatus::InGame)
+        {
+            return Err(Rejection::InProgress);
+        }
+
+        if ban {
+            self.ban(user_key, target);
+        }
+
+        self.leave_room(host, target);
+        Ok(target)
+    }
+
+    pub fn leave_room(&mut self, host: &mut impl Host<K>, user_key: K) {
+        self.queue.retain(|queued| queued.user_key != user_key);
+        let room = match self.memberships.remove(&user_key) {
+            Some(room) => room,
+            None => return,
+        };
+
+        if let Some(lobby_room) = self.rooms.get_mut(&room) {
+            if let Some(player) = lobby_room.remove(user_key) {
+                host.despawn(player);
+                self.finished.extend(lobby_room.abandon());
             }
+
+            if let Some(spectator) = lobby_room.spectators.remove(&user_key) {
+                host.despawn(spectator);
+            }
+
+            host.leave_room(user_key, room);
         }
 
-        None
+        self.tidy(host);
     }
 
-    pub fn get_user(&self, user_key: UserKey) -> Option<Entity> {
-        if let Some(&user) = self.users.get(&user_key) {
-            return Some(user);
+    pub fn match_queue(&mut self, host: &mut impl Host<K>, bot_wait: Duration) -> Vec<Match<K>> {
+        let mut matches = Vec::new();
+        while let Some((group, players, variant)) = self.next_match(bot_wait) {
+            let queued = group
+                .into_iter()
+                .map(|index| (self.queue[index].user_key, self.queue[index].name.clone()))
+                .collect::<Vec<_>>();
+
+            let (owner, name) = &queued[0];
+            let (player, room, code) =
+                self.spawn_room(host, *owner, name, None, false, Rules::new(variant));
+            let mut seated = vec![(player, *owner)];
+            for (user_key, name) in queued.into_iter().skip(1) {
+                if let Ok(player) = self.enter_room(host, room, user_key, &name, false) {
+                    seated.push((player, user_key));
+                }
+            }
+
+            let lobby_room = self.rooms.get_mut(&room).unwrap();
+            let mut bots = Vec::new();
+            while lobby_room.seats.len() < players {
+                let bot = host.spawn_member(room);
+                lobby_room.seats.push(Seat {
+                    joined: Instant::now(),
+                    name: String::new(),
+                    player: bot,
+                    ready: true,
+                    user_key: None,
+                });
+                bots.push(bot);
+            }
+
+            lobby_room.started = true;
+            lobby_room.status = RoomStatus::InGame;
+            matches.push(Match {
+                bots,
+                code,
+                players: seated,
+                room,
+            });
         }
 
-        None
+        matches
     }
 
-    pub fn leave_room<'world, 'state>(
+    pub fn members(&self, room: Entity) -> Option<(Vec<Entity>, Vec<Entity>)> {
+        self.rooms.get(&room).map(|room| {
+            (
+                room.seats.iter().map(|seat| seat.player).collect(),
+                room.spectators.values().copied().collect(),
+            )
+        })
+    }
+
+    pub fn move_seat(&mut self, user_key: K, player: Entity, seat: usize) -> bool {
+        let room = match self.seated_mut(user_key) {
+            Some(room) if room.status != RoomStatus::InGame && seat < room.seats.len() => room,
+            _ => return false,
+        };
+
+        match room.seats.iter().position(|s| s.player == player) {
+            Some(index) => {
+                let moved = room.seats.remove(index);
+                room.seats.insert(seat, moved);
+                room.unready();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn online(&self) -> impl Iterator<Item = K> + '_ {
+        self.users
+            .keys()
+            .filter(|user_key| !self.orphans.contains_key(user_key))
+            .copied()
+    }
+
+    /// Keeps the entities of a disconnected user alive until it resumes or `expired` reports it.
+    pub fn orphan(&mut self, user_key: K) -> bool {
+        if !self.users.contains_key(&user_key) {
+            return false;
+        }
+
+        self.orphans.insert(user_key, Instant::now());
+        self.queue.retain(|queued| queued.user_key != user_key);
+        true
+    }
+
+    /// The password hash of a locked room, to check entering users against.
+    pub fn password(&self, room: Entity) -> Option<&str> {
+        self.rooms.get(&room)?.password.as_deref()
+    }
+
+    pub fn queue(
+        &mut self,
+        user_key: K,
+        name: &str,
+        players: Option<usize>,
+        variant: Option<Variant>,
+    ) -> bool {
+        if self.memberships.contains_key(&user_key) {
+            return false;
+        }
+
+        let rules = Rules::new(Variant::Podkidnoy);
+        let players = players.map(|n| n.clamp(rules.min_players(), rules.max_players()));
+        let since = match self
+            .queue
+            .iter()
+            .position(|queued| queued.user_key == user_key)
+        {
+            Some(index) => self.queue.remove(index).since,
+            None => Instant::now(),
+        };
+
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| queued.since > since)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(
+            index,
+            Queued {
+                name: name.to_string(),
+                players,
+                since,
+                user_key,
+                variant,
+            },
+        );
+
+        true
+    }
+
+    /// The first player back spawns the room along with its bots and owns it until the saved
+    /// owner is back.
+    pub fn reclaim(
         &mut self,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-        user_key: UserKey,
-    ) -> Option<(Entity, Entity)> {
-        let mut res = None;
-        for (room_key, room) in &mut self.rooms {
-            if let Some(player) = room.players.remove(&user_key) {
-                server.entity_mut(&player).despawn();
-                server.user_mut(&user_key).leave_room(&room_key);
-                if let Some(&successor) = room.players.values().next() {
-                    res = Some((room.entity, successor));
+        host: &mut impl Host<K>,
+        user_key: K,
+        name: &str,
+        token: &str,
+    ) -> Option<Reclaim> {
+        let lowercase = name.to_lowercase();
+        let (index, seat) = self.saved.iter().enumerate().find_map(|(index, saved)| {
+            saved
+                .room
+                .seats
+                .iter()
+                .position(|seat| {
+                    seat.token.as_deref() == Some(token)
+                        && matches!(&seat.name, Some(own) if own.to_lowercase() == lowercase)
+                })
+                .map(|seat| (index, seat))
+        })?;
+
+        self.leave_room(host, user_key);
+        let spawned = self.saved[index]
+            .entity
+            .filter(|room| self.rooms.contains_key(room));
+        let mut bots = Vec::new();
+        let mut code = None;
+        let room = match spawned {
+            Some(room) => room,
+            None => {
+                // Another room may have taken the code in the meantime
+                let saved = &self.saved[index].room;
+                let own_code = match self.code_room(&saved.code) {
+                    Some(_) => self.unique_code(),
+                    None => saved.code.clone(),
+                };
+
+                let saved = &self.saved[index].room;
+                let room = host.create_room(saved.private);
+                let mut seats = Vec::new();
+                for _ in saved.seats.iter().filter(|seat| seat.name.is_none()) {
+                    let bot = host.spawn_member(room);
+                    seats.push(Seat {
+                        joined: Instant::now(),
+                        name: String::new(),
+                        player: bot,
+                        ready: true,
+                        user_key: None,
+                    });
+                    bots.push(bot);
                 }
+
+                let variant = match saved.variant.as_str() {
+                    "perevodnoy" => Variant::Perevodnoy,
+                    _ => Variant::Podkidnoy,
+                };
+
+                self.rooms.insert(
+                    room,
+                    LobbyRoom {
+                        active: Instant::now(),
+                        banned: saved.banned.iter().cloned().collect(),
+                        code: own_code.clone(),
+                        forfeits: Vec::new(),
+                        owner: user_key,
+                        password: saved.password.clone(),
+                        private: saved.private,
+                        rules: Rules::new(variant),
+                        seats,
+                        spectators: HashMap::new(),
+                        started: true,
+                        status: RoomStatus::InGame,
+                    },
+                );
+
+                self.saved[index].entity = Some(room);
+                code = Some(own_code);
+                room
             }
+        };
+
+        let saved_seat = &mut self.saved[index].room.seats[seat];
+        saved_seat.token = None;
+        let owner = saved_seat.owner;
+        let lobby_room = self.rooms.get_mut(&room)?;
+        let player = host.spawn_member(room);
+
+        // Players that are not back yet leave gaps, so the order is only kept roughly
+        let position = seat.min(lobby_room.seats.len());
+        lobby_room.seats.insert(
+            position,
+            Seat {
+                joined: Instant::now(),
+                name: name.to_string(),
+                player,
+                ready: false,
+                user_key: Some(user_key),
+            },
+        );
+
+        if owner {
+            lobby_room.owner = user_key;
         }
 
-        self.tidy(server);
-        res
+        let locked = lobby_room.password.is_some();
+        host.enter_room(user_key, room);
+        self.memberships.insert(user_key, room);
+        Some(Reclaim {
+            bots,
+            code,
+            locked,
+            player,
+            room,
+        })
     }
 
-    pub fn register<'world, 'state>(
+    pub fn register(
         &mut self,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-        user_key: UserKey,
-    ) -> Entity {
-        let user = server.spawn().enter_room(&self.lobby_key).id();
+        host: &mut impl Host<K>,
+        user_key: K,
+        identity: Identity,
+    ) -> (Entity, String) {
+        let user = host.spawn_user();
         self.users.insert(user_key, user);
-        server.user_mut(&user_key).enter_room(&self.lobby_key);
-        user
+        self.identities.insert(user_key, identity);
+        self.activity.insert(
+            user_key,
+            Activity {
+                since: Instant::now(),
+                warned: false,
+            },
+        );
+        host.enter_lobby(user_key);
+
+        let token = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .map(char::from)
+            .collect::<String>();
+
+        self.tokens.insert(token.clone(), user_key);
+        (user, token)
     }
 
-    pub fn spawn_room<'world, 'state>(
+    /// The session stays orphaned until `resume` is called for it.
+    pub fn rekey(&mut self, token: &str, user_key: K) -> bool {
+        let old_key = match self.tokens.get(token) {
+            Some(&old_key) if self.orphans.contains_key(&old_key) => old_key,
+            _ => return false,
+        };
+
+        let since = self.orphans.remove(&old_key).unwrap();
+        self.orphans.insert(user_key, since);
+        self.tokens.insert(token.to_string(), user_key);
+        if let Some(user) = self.users.remove(&old_key) {
+            self.users.insert(user_key, user);
+        }
+
+        if let Some(activity) = self.activity.remove(&old_key) {
+            self.activity.insert(user_key, activity);
+        }
+
+        if let Some(identity) = self.identities.remove(&old_key) {
+            self.identities.insert(user_key, identity);
+        }
+
+        let room = match self.memberships.remove(&old_key) {
+            Some(room) => room,
+            None => return true,
+        };
+
+        self.memberships.insert(user_key, room);
+        if let Some(room) = self.rooms.get_mut(&room) {
+            for seat in room.seats.iter_mut() {
+                if seat.user_key == Some(old_key) {
+                    seat.user_key = Some(user_key);
+                }
+            }
+
+            if room.owner == old_key {
+                room.owner = user_key;
+            }
+
+            if let Some(spectator) = room.spectators.remove(&old_key) {
+                room.spectators.insert(user_key, spectator);
+            }
+        }
+
+        true
+    }
+
+    pub fn restore(&mut self, rooms: Vec<SavedRoom>) {
+        let since = Instant::now();
+        self.saved.extend(rooms.into_iter().map(|room| Saved {
+            entity: None,
+            room,
+            since,
+        }));
+    }
+
+    pub fn resume(
         &mut self,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-        user_key: UserKey,
-    ) -> (Entity, Entity) {
-        let room = server.spawn().enter_room(&self.lobby_key).id();
-        let room_key = server.make_room().key();
+        host: &mut impl Host<K>,
+        user_key: K,
+    ) -> Option<(Entity, String, Option<Entity>)> {
+        self.orphans.remove(&user_key)?;
+        let user = *self.users.get(&user_key)?;
+        let token = self.token(user_key)?;
+        host.enter_lobby(user_key);
+        self.touch(user_key);
+
+        let room = self.room_of(user_key);
+        if let Some(room) = room {
+            host.enter_room(user_key, room);
+        }
+
+        Some((user, token, room))
+    }
+
+    pub fn room_mates(&self, user_key: K) -> Vec<K> {
+        match self
+            .room_of(user_key)
+            .and_then(|room| self.rooms.get(&room))
+        {
+            Some(room) => room
+                .seats
+                .iter()
+                .filter_map(|seat| seat.user_key)
+                .chain(room.spectators.keys().copied())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn room_of(&self, user_key: K) -> Option<Entity> {
+        self.memberships.get(&user_key).copied()
+    }
+
+    pub fn room_summaries(&self) -> impl Iterator<Item = Summary<'_>> + '_ {
+        self.rooms.iter().map(|(&entity, room)| Summary {
+            max_players: room.rules.max_players(),
+            owner: room.player(room.owner),
+            players: room.seats.len(),
+            room: entity,
+            status: &room.status,
+            variant: &room.rules.variant,
+        })
+    }
+
+    pub fn seats(&self) -> impl Iterator<Item = (Entity, usize, bool, bool)> + '_ {
+        self.rooms.values().flat_map(|room| {
+            room.seats.iter().enumerate().map(|(index, seat)| {
+                (
+                    seat.player,
+                    index,
+                    seat.ready,
+                    seat.user_key == Some(room.owner),
+                )
+            })
+        })
+    }
+
+    pub fn set_ready(&mut self, user_key: K, ready: bool) -> bool {
+        let room = match self.seated_mut(user_key) {
+            Some(room) if room.status != RoomStatus::InGame => room,
+            _ => return false,
+        };
+
+        match room
+            .seats
+            .iter_mut()
+            .find(|seat| seat.user_key == Some(user_key))
+        {
+            Some(seat) => {
+                seat.ready = ready;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restored games that still wait for players are saved again, so nobody loses a seat
+    /// to a second restart.
+    pub fn snapshot(&self, name: impl Fn(K) -> Option<String>) -> Vec<SavedRoom> {
+        let waiting = self
+            .saved
+            .iter()
+            .filter(|saved| saved.entity.is_none())
+            .map(|saved| saved.room.clone());
+
+        self.rooms
+            .iter()
+            .filter(|(_, room)| room.status == RoomStatus::InGame)
+            .map(|(entity, room)| SavedRoom {
+                banned: room.banned.iter().cloned().collect(),
+                code: room.code.clone(),
+                password: room.password.clone(),
+                private: room.private,
+                seats: room
+                    .seats
+                    .iter()
+                    .map(|seat| match seat.user_key {
+                        Some(user_key) => SavedSeat {
+                            name: name(user_key),
+                            owner: room.owner == user_key,
+                            token: self.token(user_key),
+                        },
+                        None => SavedSeat {
+                            name: None,
+                            owner: false,
+                            token: None,
+                        },
+                    })
+                    .chain(self.absent(*entity))
+                    .collect(),
+                variant: match room.rules.variant {
+                    Variant::Perevodnoy => "perevodnoy",
+                    Variant::Podkidnoy => "podkidnoy",
+                }
+                .to_string(),
+            })
+            .chain(waiting)
+            .collect()
+    }
+
+    pub fn spawn_room(
+        &mut self,
+        host: &mut impl Host<K>,
+        user_key: K,
+        name: &str,
+        password: Option<String>,
+        private: bool,
+        rules: Rules,
+    ) -> (Entity, Entity, String) {
+        self.leave_room(host, user_key);
+        let room = host.create_room(private);
+        let code = self.unique_code();
         self.rooms.insert(
-            room_key,
+            room,
             LobbyRoom {
-                entity: room,
-                players: HashMap::new(),
+                active: Instant::now(),
+                banned: HashSet::new(),
+                code: code.clone(),
+                forfeits: Vec::new(),
+                owner: user_key,
+                password,
+                private,
+                rules,
+                seats: Vec::new(),
+                spectators: HashMap::new(),
+                started: false,
+                status: RoomStatus::Waiting,
             },
         );
 
-        (self.enter_room(room, server, user_key).unwrap(), room)
+        let player = self
+            .sit(host, room, user_key, name)
+            .unwrap_or_else(|| unreachable!("the new room exists"));
+
+        (player, room, code)
     }
 
-    fn tidy<'world, 'state>(
+    /// Asking to start counts as being ready.
+    pub fn start_game(&mut self, user_key: K) -> bool {
+        let room = match self.seated_mut(user_key) {
+            Some(room) => room,
+            None => return false,
+        };
+
+        if room.status == RoomStatus::InGame
+            || room.seats.len() < room.rules.min_players()
+            || room
+                .seats
+                .iter()
+                .any(|seat| !seat.ready && seat.user_key != Some(user_key))
+        {
+            return false;
+        }
+
+        room.started = true;
+        room.status = RoomStatus::InGame;
+        room.unready();
+        true
+    }
+
+    pub fn take_finished(&mut self) -> Vec<GameResult> {
+        std::mem::take(&mut self.finished)
+    }
+
+    pub fn touch(&mut self, user_key: K) {
+        if let Some(activity) = self.activity.get_mut(&user_key) {
+            activity.since = Instant::now();
+            activity.warned = false;
+        }
+
+        let room = self.memberships.get(&user_key);
+        if let Some(room) = room.and_then(|room| self.rooms.get_mut(room)) {
+            room.active = Instant::now();
+        }
+    }
+
+    pub fn transfer(&mut self, user_key: K, player: Entity) -> bool {
+        let successor = match self.fellow(user_key, player) {
+            Some(successor) => successor,
+            None => return false,
+        };
+
+        match self.seated_mut(user_key) {
+            Some(room) if room.owner == user_key => {
+                room.owner = successor;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The entities of all users, including orphans that may still resume.
+    pub fn users(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.users.values().copied()
+    }
+
+    pub fn watch_room(
+        &mut self,
+        host: &mut impl Host<K>,
+        room: Entity,
+        user_key: K,
+        unlocked: bool,
+    ) -> Result<Entity, Rejection> {
+        let lobby_room = self.rooms.get(&room).ok_or(Rejection::NotFound)?;
+        if let Some(&spectator) = lobby_room.spectators.get(&user_key) {
+            return Ok(spectator);
+        }
+
+        lobby_room.admit(self.identities.get(&user_key), unlocked)?;
+        self.leave_room(host, user_key);
+        let lobby_room = self.rooms.get_mut(&room).ok_or(Rejection::NotFound)?;
+        let spectator = host.spawn_member(room);
+        lobby_room.spectators.insert(user_key, spectator);
+        host.enter_room(user_key, room);
+        self.memberships.insert(user_key, room);
+        Ok(spectator)
+    }
+
+    /// Seats of the restored game in `room` whose players are not back yet.
+    fn absent(&self, room: Entity) -> Vec<SavedSeat> {
+        self.saved
+            .iter()
+            .filter(|saved| saved.entity == Some(room))
+            .flat_map(|saved| saved.room.seats.iter())
+            .filter(|seat| seat.token.is_some())
+            .cloned()
+            .collect()
+    }
+
+    fn next_match(&self, bot_wait: Duration) -> Option<(Vec<usize>, usize, Variant)> {
+        for (index, anchor) in self.queue.iter().enumerate() {
+            let mut group = vec![index];
+            let mut players = anchor.players;
+            let mut variant = anchor.variant.clone();
+            for (other_index, other) in self.queue.iter().enumerate().skip(index + 1) {
+                if group.len() >= players.unwrap_or(QUICK_PLAYERS) {
+                    break;
+                }
+
+                if other.accepts(group.len(), players, &variant) {
+                    group.push(other_index);
+                    players = players.or(other.players);
+                    variant = variant.or_else(|| other.variant.clone());
+                }
+            }
+
+            let players = players.unwrap_or(QUICK_PLAYERS);
+            if group.len() >= players || anchor.since.elapsed() >= bot_wait {
+                return Some((group, players, variant.unwrap_or(Variant::Podkidnoy)));
+            }
+        }
+
+        None
+    }
+
+    /// Returns the room `user_key` sits in, not counting rooms it only watches.
+    fn seated(&self, user_key: K) -> Option<&LobbyRoom<K>> {
+        self.memberships
+            .get(&user_key)
+            .and_then(|room| self.rooms.get(room))
+            .filter(|room| room.contains(user_key))
+    }
+
+    fn seated_mut(&mut self, user_key: K) -> Option<&mut LobbyRoom<K>> {
+        self.memberships
+            .get(&user_key)
+            .and_then(|room| self.rooms.get_mut(room))
+            .filter(|room| room.contains(user_key))
+    }
+
+    fn sit(
         &mut self,
-        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
-    ) {
-        self.rooms.retain(|room_key, room| {
-            let retain = !room.players.is_empty();
-            if !retain {
-                server.room_mut(room_key).destroy();
-                server
-                    .entity_mut(&room.entity)
-                    .leave_room(&self.lobby_key)
-                    .despawn();
-            }
-
-            retain
+        host: &mut impl Host<K>,
+        room: Entity,
+        user_key: K,
+        name: &str,
+    ) -> Option<Entity> {
+        let lobby_room = self.rooms.get_mut(&room)?;
+        let player = host.spawn_member(room);
+        lobby_room.unready();
+        lobby_room.seats.push(Seat {
+            joined: Instant::now(),
+            name: name.to_string(),
+            player,
+            ready: false,
+            user_key: Some(user_key),
         });
+
+        host.enter_room(user_key, room);
+        self.memberships.insert(user_key, room);
+        Some(player)
+    }
+
+    /// Closes the rooms without any users seated. Bots do not play on their own, and nobody
+    /// is left to watch.
+    fn tidy(&mut self, host: &mut impl Host<K>) {
+        let closed = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| room.seats.iter().all(|seat| seat.user_key.is_none()))
+            .map(|(&entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        for room in closed {
+            let lobby_room = self.rooms.remove(&room).unwrap();
+            for seat in lobby_room.seats {
+                host.despawn(seat.player);
+            }
+
+            for (user_key, spectator) in lobby_room.spectators {
+                host.despawn(spectator);
+                host.leave_room(user_key, room);
+                self.memberships.remove(&user_key);
+            }
+
+            host.destroy_room(room);
+        }
+    }
+
+    fn token(&self, user_key: K) -> Option<String> {
+        self.tokens
+            .iter()
+            .find(|(_, &key)| key == user_key)
+            .map(|(token, _)| token.clone())
+    }
+
+    fn unique_code(&self) -> String {
+        let mut rng = thread_rng();
+        loop {
+            let code = (0..CODE_LEN)
+                .map(|_| CODE_CHARS[rng.gen_range(0..CODE_CHARS.len())] as char)
+                .collect::<String>();
+
+            if self.rooms.values().all(|room| room.code != code) {
+                return code;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::logic::accounts;
+
+    #[derive(Default)]
+    struct FakeHost {
+        despawned: Vec<Entity>,
+        destroyed: Vec<Entity>,
+        next: u32,
+    }
+
+    impl Host<u32> for FakeHost {
+        fn create_room(&mut self, _private: bool) -> Entity {
+            self.spawn_user()
+        }
+
+        fn despawn(&mut self, entity: Entity) {
+            self.despawned.push(entity);
+        }
+
+        fn destroy_room(&mut self, room: Entity) {
+            self.destroyed.push(room);
+        }
+
+        fn enter_lobby(&mut self, _user_key: u32) {}
+
+        fn enter_room(&mut self, _user_key: u32, _room: Entity) {}
+
+        fn leave_room(&mut self, _user_key: u32, _room: Entity) {}
+
+        fn spawn_member(&mut self, _room: Entity) -> Entity {
+            self.spawn_user()
+        }
+
+        fn spawn_user(&mut self) -> Entity {
+            self.next += 1;
+            Entity::from_raw(self.next)
+        }
+    }
+
+    fn identity(user_key: u32) -> Identity {
+        Identity::Account(user_key.to_string())
+    }
+
+    fn rules() -> Rules {
+        Rules::new(Variant::Podkidnoy)
+    }
+
+    fn setup(users: u32) -> (FakeHost, Lobby<u32>) {
+        let mut host = FakeHost::default();
+        let mut lobby = Lobby::new();
+        for user_key in 0..users {
+            lobby.register(&mut host, user_key, identity(user_key));
+        }
+
+        (host, lobby)
+    }
+
+    #[test]
+    fn entering_a_room_leaves_the_old_one() {
+        let (mut host, mut lobby) = setup(2);
+        let (player, old, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        let (_, new, _) = lobby.spawn_room(&mut host, 1, "b", None, false, rules());
+
+        assert!(lobby.enter_room(&mut host, new, 0, "a", false).is_ok());
+        assert_eq!(lobby.room_of(0), Some(new));
+        assert!(host.despawned.contains(&player));
+        assert!(host.destroyed.contains(&old));
+        assert_eq!(lobby.room_mates(0).len(), 2);
+    }
+
+    #[test]
+    fn stale_rooms_close() {
+        let (mut host, mut lobby) = setup(4);
+        let (_, stale, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.watch_room(&mut host, stale, 1, false).is_ok());
+        let (_, started, _) = lobby.spawn_room(&mut host, 2, "c", None, false, rules());
+        assert!(lobby.enter_room(&mut host, started, 3, "d", false).is_ok());
+        assert!(lobby.set_ready(3, true));
+        assert!(lobby.start_game(2));
+        assert!(lobby
+            .close_stale(&mut host, Duration::from_secs(60))
+            .is_empty());
+
+        let closed = lobby.close_stale(&mut host, Duration::ZERO);
+        assert_eq!(closed.len(), 2);
+        assert!(closed.contains(&0) && closed.contains(&1));
+        assert!(host.destroyed.contains(&stale));
+        assert_eq!(lobby.room_of(1), None);
+        assert_eq!(lobby.room_of(2), Some(started));
+    }
+
+    #[test]
+    fn active_rooms_stay_open() {
+        let (mut host, mut lobby) = setup(2);
+        let (_, active, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        let (_, idle, _) = lobby.spawn_room(&mut host, 1, "b", None, false, rules());
+        thread::sleep(Duration::from_millis(20));
+        lobby.touch(0);
+
+        let closed = lobby.close_stale(&mut host, Duration::from_millis(20));
+        assert_eq!(closed, vec![1]);
+        assert!(host.destroyed.contains(&idle));
+        assert_eq!(lobby.room_of(0), Some(active));
+    }
+
+    #[test]
+    fn spawning_a_room_leaves_the_old_one() {
+        let (mut host, mut lobby) = setup(2);
+        let (_, old, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.enter_room(&mut host, old, 1, "b", false).is_ok());
+
+        let (_, new, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert_eq!(lobby.room_of(0), Some(new));
+        assert_eq!(lobby.room_of(1), Some(old));
+        assert!(lobby.is_owner(1));
+    }
+
+    #[test]
+    fn watching_a_room_leaves_the_seat() {
+        let (mut host, mut lobby) = setup(2);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.enter_room(&mut host, room, 1, "b", false).is_ok());
+        assert!(lobby.watch_room(&mut host, room, 1, false).is_ok());
+
+        assert_eq!(lobby.room_of(1), Some(room));
+        assert_eq!(lobby.seats().count(), 1);
+    }
+
+    #[test]
+    fn idle_users_are_warned_then_moved_or_disconnected() {
+        let (mut host, mut lobby) = setup(2);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        let idle = lobby.idle(&mut host, Duration::ZERO, Duration::from_secs(60));
+        assert_eq!(idle.len(), 2);
+        assert!(idle
+            .iter()
+            .all(|(_, idle)| matches!(idle, Idle::Warning(_))));
+        assert!(lobby
+            .idle(&mut host, Duration::ZERO, Duration::from_secs(60))
+            .is_empty());
+
+        let idle = lobby.idle(&mut host, Duration::ZERO, Duration::ZERO);
+        assert!(idle
+            .iter()
+            .any(|(user_key, idle)| *user_key == 0 && matches!(idle, Idle::Moved)));
+        assert!(idle
+            .iter()
+            .any(|(user_key, idle)| *user_key == 1 && matches!(idle, Idle::Disconnect)));
+        assert_eq!(lobby.room_of(0), None);
+        assert!(host.destroyed.contains(&room));
+    }
+
+    #[test]
+    fn players_in_a_game_are_not_idle() {
+        let (mut host, mut lobby) = setup(3);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.enter_room(&mut host, room, 1, "b", false).is_ok());
+        assert!(lobby.enter_room(&mut host, room, 2, "c", false).is_ok());
+        assert!(lobby.watch_room(&mut host, room, 2, false).is_ok());
+        assert!(lobby.set_ready(1, true));
+        assert!(lobby.start_game(0));
+
+        let idle = lobby.idle(&mut host, Duration::ZERO, Duration::ZERO);
+        assert_eq!(idle.len(), 1);
+        assert!(matches!(idle[0], (2, Idle::Moved)));
+        assert_eq!(lobby.room_of(0), Some(room));
+        assert_eq!(lobby.room_of(1), Some(room));
+        assert!(lobby.take_finished().is_empty());
+    }
+
+    #[test]
+    fn kicks_wait_for_the_game_to_end() {
+        let (mut host, mut lobby) = setup(3);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        let b = lobby
+            .enter_room(&mut host, room, 1, "b", false)
+            .ok()
+            .unwrap();
+        let c = lobby
+            .enter_room(&mut host, room, 2, "c", false)
+            .ok()
+            .unwrap();
+
+        let res = lobby.kick(&mut host, 1, c, false);
+        assert!(matches!(res, Err(Rejection::NotFound)));
+        assert!(matches!(lobby.kick(&mut host, 0, c, false), Ok(2)));
+        assert_eq!(lobby.room_of(2), None);
+
+        assert!(lobby.set_ready(1, true));
+        assert!(lobby.start_game(0));
+        let res = lobby.kick(&mut host, 0, b, true);
+        assert!(matches!(res, Err(Rejection::InProgress)));
+        assert_eq!(lobby.room_of(1), Some(room));
+        assert!(lobby.take_finished().is_empty());
+    }
+
+    #[test]
+    fn ownership_passes_to_the_longest_present() {
+        let (mut host, mut lobby) = setup(3);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.enter_room(&mut host, room, 1, "b", false).is_ok());
+        assert!(lobby.enter_room(&mut host, room, 2, "c", false).is_ok());
+
+        lobby.leave_room(&mut host, 0);
+        assert!(lobby.is_owner(1));
+        assert!(!lobby.is_owner(2));
+        assert_eq!(lobby.room_of(0), None);
+    }
+
+    #[test]
+    fn rejects_passwords_bans_and_full_rooms() {
+        let (mut host, mut lobby) = setup(8);
+        let (_, room, _) =
+            lobby.spawn_room(&mut host, 0, "a", Some("pw".to_string()), false, rules());
+
+        let res = lobby.enter_room(&mut host, room, 1, "b", false);
+        assert!(matches!(res, Err(Rejection::Password)));
+        assert!(lobby.enter_room(&mut host, room, 1, "b", true).is_ok());
+
+        lobby.ban(0, 1);
+        lobby.leave_room(&mut host, 1);
+        let res = lobby.enter_room(&mut host, room, 1, "b", true);
+        assert!(matches!(res, Err(Rejection::Banned)));
+        let res = lobby.enter_room(&mut host, room, 1, "renamed", true);
+        assert!(matches!(res, Err(Rejection::Banned)));
+
+        for user_key in 2..7 {
+            let name = user_key.to_string();
+            assert!(lobby
+                .enter_room(&mut host, room, user_key, &name, true)
+                .is_ok());
+        }
+
+        let res = lobby.enter_room(&mut host, room, 7, "h", true);
+        assert!(matches!(res, Err(Rejection::Full)));
+        assert_eq!(lobby.room_of(7), None);
+    }
+
+    #[test]
+    fn starts_once_everyone_is_ready() {
+        let (mut host, mut lobby) = setup(3);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(!lobby.start_game(0));
+
+        assert!(lobby.enter_room(&mut host, room, 1, "b", false).is_ok());
+        assert!(!lobby.start_game(0));
+
+        assert!(lobby.set_ready(1, true));
+        assert!(lobby.start_game(0));
+
+        let res = lobby.enter_room(&mut host, room, 2, "c", false);
+        assert!(matches!(res, Err(Rejection::InProgress)));
+    }
+
+    #[test]
+    fn walking_out_of_a_game_loses_it() {
+        let (mut host, mut lobby) = setup(2);
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+        assert!(lobby.enter_room(&mut host, room, 1, "b", false).is_ok());
+        assert!(lobby.set_ready(1, true));
+        assert!(lobby.start_game(0));
+
+        lobby.leave_room(&mut host, 1);
+        let finished = lobby.take_finished();
+        assert_eq!(finished.len(), 1);
+        let players = &finished[0].players;
+        assert_eq!(players.len(), 2);
+        assert!(players[0].name == "a" && !players[0].durak);
+        assert!(players[1].name == "b" && players[1].durak);
+        assert!(lobby.take_finished().is_empty());
+    }
+
+    #[test]
+    fn rekey_keeps_the_room() {
+        let (mut host, mut lobby) = setup(0);
+        let (_, token) = lobby.register(&mut host, 0, identity(0));
+        let (_, room, _) = lobby.spawn_room(&mut host, 0, "a", None, false, rules());
+
+        assert!(lobby.orphan(0));
+        assert!(lobby.rekey(&token, 5));
+        assert_eq!(lobby.room_of(5), Some(room));
+        assert_eq!(lobby.room_of(0), None);
+        assert!(lobby.is_owner(5));
+
+        let (_, _, resumed) = lobby.resume(&mut host, 5).unwrap();
+        assert_eq!(resumed, Some(room));
+    }
+
+    #[test]
+    fn quick_match_against_bots_only_is_not_recorded() {
+        let (mut host, mut lobby) = setup(1);
+        assert!(lobby.queue(0, "a", None, None));
+        let matches = lobby.match_queue(&mut host, Duration::ZERO);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players.len(), 1);
+
+        lobby.leave_room(&mut host, 0);
+        assert!(lobby.take_finished().is_empty());
+    }
+
+    #[test]
+    fn quick_match_waits_then_fills_with_bots() {
+        let (mut host, mut lobby) = setup(2);
+        assert!(lobby.queue(0, "a", Some(3), None));
+        assert!(lobby.queue(1, "b", None, None));
+        assert!(lobby
+            .match_queue(&mut host, Duration::from_secs(60))
+            .is_empty());
+
+        let matches = lobby.match_queue(&mut host, Duration::ZERO);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players.len(), 2);
+        assert_eq!(matches[0].bots.len(), 1);
+        assert_eq!(lobby.room_of(0), Some(matches[0].room));
+        assert_eq!(lobby.room_of(1), Some(matches[0].room));
+        assert!(!lobby.queue(0, "a", None, None));
+
+        // Bots do not keep a game going, and they leave with the last user
+        let room = matches[0].room;
+        lobby.leave_room(&mut host, 0);
+        assert_eq!(lobby.take_finished().len(), 1);
+        lobby.leave_room(&mut host, 1);
+        assert!(host.destroyed.contains(&room));
+        assert!(host.despawned.contains(&matches[0].bots[0]));
+    }
+
+    #[test]
+    fn restored_games_wait_for_their_players() {
+        let (mut host, mut lobby) = setup(3);
+        let password = "open sesame";
+        let hash = accounts::hash(password);
+        let (_, room, code) = lobby.spawn_room(&mut host, 0, "a", hash, false, rules());
+        assert!(lobby.enter_room(&mut host, room, 1, "b", true).is_ok());
+        assert!(lobby.set_ready(1, true));
+        assert!(lobby.start_game(0));
+        let names = ["a", "b", "c"];
+        let tokens = (0..2)
+            .map(|user_key| lobby.token(user_key).unwrap())
+            .collect::<Vec<_>>();
+        let saved = lobby.snapshot(|user_key| Some(names[user_key as usize].to_string()));
+        assert_eq!(saved.len(), 1);
+        assert!(!serde_json::to_string(&saved).unwrap().contains(password));
+
+        let (mut host, mut lobby) = setup(3);
+        lobby.restore(saved);
+        assert_eq!(lobby.snapshot(|_| None).len(), 1);
+        assert!(lobby.reclaim(&mut host, 2, "c", &tokens[0]).is_none());
+
+        let reclaim = lobby.reclaim(&mut host, 1, "B", &tokens[1]).unwrap();
+        assert_eq!(reclaim.code, Some(code));
+        assert_eq!(lobby.room_of(1), Some(reclaim.room));
+        assert!(lobby.is_owner(1));
+        assert!(lobby.reclaim(&mut host, 2, "b", &tokens[1]).is_none());
+
+        // A second restart keeps the seat of the player that is not back yet
+        let grace = Duration::from_secs(60);
+        lobby.forget_saved(grace);
+        assert!(lobby.has_saved());
+        let saved = lobby.snapshot(|user_key| Some(names[user_key as usize].to_string()));
+        assert_eq!(saved.len(), 1);
+        assert!(serde_json::to_string(&saved).unwrap().contains(&tokens[0]));
+
+        let back = lobby.reclaim(&mut host, 0, "a", &tokens[0]).unwrap();
+        assert_eq!(back.room, reclaim.room);
+        assert_eq!(back.code, None);
+        assert!(lobby.is_owner(0));
+        lobby.forget_saved(grace);
+        assert!(!lobby.has_saved());
+        let res = lobby.enter_room(&mut host, reclaim.room, 2, "c", false);
+        assert!(matches!(res, Err(Rejection::Password)));
+        let res = lobby.enter_room(&mut host, reclaim.room, 2, "c", true);
+        assert!(matches!(res, Err(Rejection::InProgress)));
+    }
+
+    #[test]
+    fn quick_match_respects_the_variant() {
+        let (mut host, mut lobby) = setup(3);
+        let wait = Duration::from_secs(60);
+        assert!(lobby.queue(0, "a", Some(2), Some(Variant::Podkidnoy)));
+        assert!(lobby.queue(1, "b", Some(2), Some(Variant::Perevodnoy)));
+        assert!(lobby.match_queue(&mut host, wait).is_empty());
+
+        assert!(lobby.queue(2, "c", None, None));
+        let matches = lobby.match_queue(&mut host, wait);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(lobby.room_of(0), Some(matches[0].room));
+        assert_eq!(lobby.room_of(1), None);
+        assert_eq!(lobby.room_of(2), Some(matches[0].room));
     }
 }
@@ -1,2 +1,8 @@
+pub mod accounts;
+pub mod flood;
 //pub mod game;
 pub mod lobby;
+pub mod rating;
+pub mod rules;
+pub mod scope;
+pub mod validation;
@@ -0,0 +1,109 @@
+use crate::storage::{GameResult, Storage};
+
+pub const RATING_INIT: f32 = 1500.0;
+const RATING_K: f32 = 32.0;
+
+/// Rates the players of a finished game and stores it, returns its id.
+pub fn record(storage: &mut dyn Storage, game: GameResult) -> Option<u64> {
+    rate(storage, &game);
+    storage.record_game(game)
+}
+
+/// Durak has a single loser, so every game is rated as if the durak had lost one Elo match
+/// against each other player. The K factor is split among those matches, which keeps a
+/// game worth the same regardless of the number of players and the ratings zero-sum.
+/// Guests have no profile, they count with the initial rating and are not updated.
+fn rate(storage: &mut dyn Storage, game: &GameResult) {
+    let durak = match game.players.iter().find(|player| player.durak) {
+        Some(durak) => durak,
+        None => return,
+    };
+
+    if game.players.len() < 2 {
+        return;
+    }
+
+    let rating = |name: &str| {
+        storage
+            .profile(name)
+            .map(|profile| profile.rating)
+            .unwrap_or(RATING_INIT)
+    };
+
+    let k = RATING_K / (game.players.len() - 1) as f32;
+    let durak_rating = rating(&durak.name);
+    let mut durak_delta = 0.0;
+    let mut updates = Vec::new();
+    for player in game.players.iter().filter(|player| !player.durak) {
+        let player_rating = rating(&player.name);
+        let delta = k * (1.0 - expected(player_rating, durak_rating));
+        durak_delta -= delta;
+        updates.push((player.name.as_str(), player_rating + delta));
+    }
+
+    updates.push((durak.name.as_str(), durak_rating + durak_delta));
+    for (name, rating) in updates {
+        storage.set_rating(name, rating);
+    }
+}
+
+/// Expected score of a player rated `a` against one rated `b`.
+fn expected(a: f32, b: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((b - a) / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{game, MemoryStorage, Profile};
+
+    fn storage(ratings: &[(&str, f32)]) -> MemoryStorage {
+        let mut storage = MemoryStorage::new();
+        for (name, rating) in ratings {
+            storage.insert_profile(Profile {
+                name: name.to_string(),
+                password: String::new(),
+                rating: *rating,
+            });
+        }
+
+        storage
+    }
+
+    fn rating(storage: &MemoryStorage, name: &str) -> f32 {
+        storage.profile(name).unwrap().rating
+    }
+
+    #[test]
+    fn durak_loses_rating() {
+        let mut storage = storage(&[("a", 1500.0), ("b", 1500.0)]);
+        rate(&mut storage, &game("b", &["a", "b"]));
+        assert_eq!(rating(&storage, "a"), 1516.0);
+        assert_eq!(rating(&storage, "b"), 1484.0);
+    }
+
+    #[test]
+    fn ratings_are_zero_sum() {
+        let ratings = [("a", 1700.0), ("b", 1450.0), ("c", 1520.0), ("d", 1300.0)];
+        let mut storage = storage(&ratings);
+        rate(&mut storage, &game("a", &["b", "c", "d", "a"]));
+
+        let before = ratings.iter().map(|(_, rating)| rating).sum::<f32>();
+        let after = ratings
+            .iter()
+            .map(|(name, _)| rating(&storage, name))
+            .sum::<f32>();
+        assert!((before - after).abs() < 0.01);
+        assert!(rating(&storage, "a") < 1700.0);
+    }
+
+    #[test]
+    fn recorded_games_are_rated() {
+        let mut storage = storage(&[("a", 1500.0), ("b", 1500.0)]);
+        assert!(record(&mut storage, game("b", &["a", "guest", "b"])).is_some());
+        assert!(rating(&storage, "a") > 1500.0);
+        assert!(rating(&storage, "b") < 1500.0);
+        assert_eq!(storage.stats("a").games, 1);
+        assert_eq!(storage.stats("b").duraks, 1);
+    }
+}
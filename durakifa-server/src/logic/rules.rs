@@ -0,0 +1,23 @@
+use durakifa_protocol::protocol::Variant;
+
+const DECK_SIZE: usize = 36;
+const HAND_SIZE: usize = 6;
+
+pub struct Rules {
+    pub variant: Variant,
+}
+
+impl Rules {
+    pub fn new(variant: Variant) -> Self {
+        Rules { variant }
+    }
+
+    /// Every player has to get a full hand from the deck.
+    pub fn max_players(&self) -> usize {
+        DECK_SIZE / HAND_SIZE
+    }
+
+    pub fn min_players(&self) -> usize {
+        2
+    }
+}
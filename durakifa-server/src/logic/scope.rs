@@ -0,0 +1,88 @@
+use bevy_ecs::prelude::Entity;
+
+/// Where a user is, which decides what it gets replicated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Context {
+    /// Browsing the room list.
+    Lobby,
+    /// Seated in or watching the room entity, waiting or playing.
+    Room(Entity),
+}
+
+/// What an entity is as far as visibility goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subject {
+    /// A player, bot or spectator of the room entity.
+    Member(Entity),
+    /// The entity carrying the online count.
+    Online,
+    Other,
+    Room(Entity),
+    /// A user entity along with the room it sits in or watches.
+    User(Entity, Option<Entity>),
+}
+
+pub struct Viewer {
+    pub context: Context,
+    pub user: Entity,
+}
+
+impl Viewer {
+    /// Lobby users get the room summaries and the online count. Users in a room get that room,
+    /// its members and their users, and nothing from the lobby. Everyone gets its own user.
+    pub fn sees(&self, subject: Subject) -> bool {
+        match (self.context, subject) {
+            (_, Subject::User(user, _)) if user == self.user => true,
+            (Context::Lobby, Subject::Online) => true,
+            (Context::Lobby, Subject::Room(_)) => true,
+            (Context::Room(own), Subject::Member(room)) => own == room,
+            (Context::Room(own), Subject::Room(room)) => own == room,
+            (Context::Room(own), Subject::User(_, Some(room))) => own == room,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OTHER: Entity = Entity::from_raw(1);
+    const OTHER_ROOM: Entity = Entity::from_raw(2);
+    const OWN: Entity = Entity::from_raw(3);
+    const OWN_ROOM: Entity = Entity::from_raw(4);
+
+    fn viewer(context: Context) -> Viewer {
+        Viewer { context, user: OWN }
+    }
+
+    #[test]
+    fn everyone_sees_itself() {
+        assert!(viewer(Context::Lobby).sees(Subject::User(OWN, None)));
+        assert!(viewer(Context::Room(OWN_ROOM)).sees(Subject::User(OWN, Some(OWN_ROOM))));
+    }
+
+    #[test]
+    fn lobby_sees_rooms_and_online_count() {
+        let lobby = viewer(Context::Lobby);
+        assert!(lobby.sees(Subject::Online));
+        assert!(lobby.sees(Subject::Room(OTHER_ROOM)));
+        assert!(!lobby.sees(Subject::Member(OTHER_ROOM)));
+        assert!(!lobby.sees(Subject::User(OTHER, None)));
+        assert!(!lobby.sees(Subject::User(OTHER, Some(OTHER_ROOM))));
+        assert!(!lobby.sees(Subject::Other));
+    }
+
+    #[test]
+    fn room_sees_only_its_own_room() {
+        let room = viewer(Context::Room(OWN_ROOM));
+        assert!(room.sees(Subject::Room(OWN_ROOM)));
+        assert!(room.sees(Subject::Member(OWN_ROOM)));
+        assert!(room.sees(Subject::User(OTHER, Some(OWN_ROOM))));
+        assert!(!room.sees(Subject::Online));
+        assert!(!room.sees(Subject::Room(OTHER_ROOM)));
+        assert!(!room.sees(Subject::Member(OTHER_ROOM)));
+        assert!(!room.sees(Subject::User(OTHER, Some(OTHER_ROOM))));
+        assert!(!room.sees(Subject::User(OTHER, None)));
+    }
+}
@@ -0,0 +1,44 @@
+use durakifa_protocol::protocol::NameError;
+use unicode_normalization::UnicodeNormalization;
+
+/// In bytes, so a chat message and the name of its sender fit into one packet.
+pub const CHAT_LEN_MAX: usize = 120;
+/// Matches the longest name the client lets users type.
+const NAME_LEN_MAX: usize = 30;
+
+/// Trims `raw` and cuts it down to `CHAT_LEN_MAX` bytes without splitting a character.
+pub fn chat(raw: &str) -> &str {
+    let text = raw.trim();
+    let mut end = text.len().min(CHAT_LEN_MAX);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    text[..end].trim_end()
+}
+
+/// Brings `raw` into NFC without surrounding whitespace. Names are unique among online users
+/// regardless of their case, `taken` tells whether a lowercase name is in use.
+pub fn name(raw: &str, taken: impl Fn(&str) -> bool) -> Result<String, NameError> {
+    let name = raw.nfc().collect::<String>().trim().to_string();
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+
+    if name.chars().count() > NAME_LEN_MAX {
+        return Err(NameError::TooLong);
+    }
+
+    if name
+        .chars()
+        .any(|c| c.is_control() || (c.is_whitespace() && c != ' '))
+    {
+        return Err(NameError::Characters);
+    }
+
+    if taken(&name.to_lowercase()) {
+        return Err(NameError::Taken);
+    }
+
+    Ok(name)
+}
@@ -1,150 +1,1090 @@
+mod admin;
+mod config;
+mod logging;
 mod logic;
+mod metrics;
+mod network;
+mod shutdown;
+mod storage;
 
-use bevy_app::{App, ScheduleRunnerPlugin};
+use std::{collections::HashMap, net::SocketAddr};
+
+use admin::{Command, Console, HELP_TXT};
+use bevy_app::{App, AppExit, CoreStage, ScheduleRunnerPlugin};
 use bevy_core::CorePlugin;
 use bevy_ecs::{
-    prelude::{Entity, EventReader},
+    prelude::{Entity, EventReader, EventWriter},
     query::{With, Without},
     schedule::IntoSystemDescriptor,
-    system::{Commands, Query, ResMut, Resource},
+    system::{Commands, Query, Res, ResMut, Resource},
+};
+use bevy_log::{debug, error, info, warn};
+use config::Config;
+use durakifa_protocol::protocol::{
+    self, Bot, Chat, Guest, IdleNotice, Idleness, JoinRejected, Kicked, Leaderboard, Name,
+    NameError, NameRejected, Online, OwnUser, Owner, Player, Protocol, Ready, Rejection, Room,
+    RoomStatus, RoomSummary, ShutdownNotice, Spectator, User, Variant,
 };
-use bevy_log::{info, LogPlugin};
-use durakifa_protocol::protocol::{Name, OwnUser, Owner, Player, Protocol, Room, User};
-use logic::lobby::Lobby;
+use logging::LogFilter;
+use logic::{
+    accounts::{self, Hasher, Outcome},
+    flood::{self, Limiter},
+    lobby::{Identity, Idle, Lobby},
+    rating,
+    rules::Rules,
+    scope::{Context, Subject, Viewer},
+    validation,
+};
+use metrics::{Gauges, Metrics, BANDWIDTH_WINDOW};
 use naia_bevy_server::{
-    events::{AuthorizationEvent, DisconnectionEvent, MessageEvent},
-    shared::{DefaultChannels, SharedConfig},
-    Plugin as ServerPlugin, Server, ServerAddrs, ServerConfig, Stage,
+    events::{AuthorizationEvent, ConnectionEvent, DisconnectionEvent, MessageEvent},
+    shared::{BigMapKey, DefaultChannels},
+    Plugin as ServerPlugin, Server, ServerAddrs, ServerConfig, Stage, UserKey,
 };
-use obfstr::obfstr;
+use naia_server::Server as NaiaServer;
+use network::Network;
+use shutdown::Shutdown;
+use storage::{MemoryStorage, SqliteStorage, Storage};
 
-const SRV_ADDR: &str = "127.0.0.1";
-const SRV_PORT: &str = "55500";
-const SRV_PORT_WRTC: &str = "55501";
+const ANNOUNCE_NAME: &str = "Server";
+const BOT_NAME: &str = "Bot";
+const LEADERBOARD_MAX: u8 = 50;
+/// Names take up to 120 bytes, so only three of them fit into naia's 508-byte packets.
+const LEADERBOARD_PAGE: usize = 3;
 
-#[cfg(debug_assertions)]
-const SRV_ADDR_PUB: &str = SRV_ADDR;
-#[cfg(not(debug_assertions))]
-const SRV_ADDR_PUB: &str = env!("SRV_ADDR");
+#[derive(Resource)]
+struct Global {
+    hasher: Hasher<Pending>,
+    /// Addresses whose sign-in or sign-up waits on the hasher. naia opens a new user for every
+    /// handshake the client resends meanwhile, those are dropped.
+    hashing: HashMap<SocketAddr, UserKey>,
+    /// Idle users told they are disconnected, which happens a tick later so the notice gets out.
+    idle_kicks: Vec<UserKey>,
+    limiter: Limiter,
+    lobby: Lobby<UserKey>,
+    logins: HashMap<UserKey, Login>,
+    metrics: Metrics,
+    network: Network,
+    online: Entity,
+    /// Users that only get to learn why their name was refused.
+    refusals: HashMap<UserKey, NameError>,
+    storage: Box<dyn Storage>,
+}
 
-#[cfg(debug_assertions)]
-const SRV_PROT: &str = "http";
-#[cfg(not(debug_assertions))]
-const SRV_PROT: &str = env!("SRV_PROT");
+/// A user that passed authorization but is not connected yet.
+struct Login {
+    /// Where the handshake came from, naia repeats it from there until it is answered.
+    address: SocketAddr,
+    guest: bool,
+    name: String,
+    /// Token of an earlier session, which may have played a game saved on shutdown.
+    token: Option<String>,
+}
 
-#[cfg(not(debug_assertions))]
-const SRV_KEY: &str = env!("SRV_KEY");
-#[cfg(debug_assertions)]
-const SRV_KEY: &str = "SRV_KEY";
+/// What waits on the hasher.
+enum Pending {
+    /// Creates the room once its password is hashed.
+    CreateRoom {
+        private: bool,
+        user_key: UserKey,
+        variant: Variant,
+    },
+    /// Seats the user, or lets it watch, once the room password is checked.
+    EnterRoom {
+        room: Entity,
+        user_key: UserKey,
+        watch: bool,
+    },
+    SignIn(UserKey),
+    /// Creates the account once its password is hashed.
+    SignUp(UserKey),
+}
 
-#[derive(Resource)]
-struct Global {
-    lobby: Lobby,
+/// Entities that are neither player, room nor user, which the dump counts as leftovers.
+type Loose = (Without<Player>, Without<Room>, Without<User>);
+
+#[allow(clippy::too_many_arguments)]
+fn admin<'world, 'state>(
+    bots: Query<&Bot>,
+    config: Res<Config>,
+    console: Res<Console>,
+    mut global: ResMut<Global>,
+    guests: Query<&Guest>,
+    log_filter: Res<LogFilter>,
+    names: Query<&Name>,
+    players: Query<(&Player, Option<&Owner>, Option<&Ready>)>,
+    rooms: Query<&Room>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    mut shutdown: ResMut<Shutdown>,
+    spectators: Query<&Spectator>,
+) {
+    let global = &mut *global;
+    for request in console.requests() {
+        let command = match Command::parse(&request.line) {
+            Ok(command) => command,
+            Err(e) => {
+                let _ = request.reply.send(e);
+                continue;
+            }
+        };
+
+        let user_name = |user: Option<Entity>| {
+            user.and_then(|user| names.get(user).ok())
+                .map_or(String::from("?"), |name| (*name.name).clone())
+        };
+
+        let room_line = |room: Entity| {
+            let summary = global
+                .lobby
+                .room_summaries()
+                .find(|summary| summary.room == room)?;
+            let code = rooms
+                .get(room)
+                .map_or(String::new(), |room| (*room.code).clone());
+            let owner = summary
+                .owner
+                .and_then(|owner| players.get(owner).ok())
+                .and_then(|(player, _, _)| player.user.get(&server));
+
+            Some(format!(
+                "{} {} {}/{} {} {}",
+                code,
+                user_name(owner),
+                summary.players,
+                summary.max_players,
+                match summary.status {
+                    RoomStatus::Finished => "finished",
+                    RoomStatus::InGame => "in game",
+                    RoomStatus::Waiting => "waiting",
+                },
+                match summary.variant {
+                    Variant::Perevodnoy => "perevodnoy",
+                    Variant::Podkidnoy => "podkidnoy",
+                },
+            ))
+        };
+
+        let find_user = |name: &str| {
+            let name = name.to_lowercase();
+            global.lobby.online().find(|&user_key| {
+                global
+                    .lobby
+                    .get_user(user_key)
+                    .and_then(|user| names.get(user).ok())
+                    .is_some_and(|user_name| user_name.name.to_lowercase() == name)
+            })
+        };
+
+        let reply = match command {
+            Command::Announce(text) => {
+                let chat = Chat::new(ANNOUNCE_NAME.to_string(), false, text);
+                for user_key in global.lobby.online() {
+                    server.send_message(&user_key, DefaultChannels::OrderedReliable, &chat);
+                }
+
+                String::from("announced")
+            }
+            Command::Ban(name, duration) => match find_user(&name) {
+                Some(user_key) => {
+                    let addr = server.user(&user_key).address().ip();
+                    global
+                        .limiter
+                        .ban(addr, duration.unwrap_or_else(|| config.flood_ban()));
+                    global
+                        .lobby
+                        .clear_user(&mut global.network.host(&mut server), user_key);
+                    server.user_mut(&user_key).disconnect();
+                    info!(user = user_key.to_u64(), %addr, "user banned by admin");
+                    format!("banned {} at {}", name, addr)
+                }
+                None => format!("no user {} online", name),
+            },
+            Command::Close(code) => match global.lobby.code_room(&code) {
+                Some(room) => {
+                    let members = global
+                        .lobby
+                        .close_room(&mut global.network.host(&mut server), room);
+                    for user_key in members.iter() {
+                        server.send_message(
+                            user_key,
+                            DefaultChannels::UnorderedReliable,
+                            &Kicked::new(false),
+                        );
+                    }
+
+                    info!(room = ?room, %code, "room closed by admin");
+                    format!("closed {}, {} users back in the lobby", code, members.len())
+                }
+                None => format!("no room {}", code),
+            },
+            Command::Dump => {
+                console.request_dump();
+                String::from("dumping rooms to the log")
+            }
+            Command::Games => global
+                .lobby
+                .room_summaries()
+                .filter(|summary| *summary.status == RoomStatus::InGame)
+                .filter_map(|summary| room_line(summary.room))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Help => HELP_TXT.to_string(),
+            Command::Inspect(code) => {
+                match global
+                    .lobby
+                    .code_room(&code)
+                    .and_then(|room| Some((room_line(room)?, global.lobby.members(room)?)))
+                {
+                    Some((line, (seated, watching))) => {
+                        let mut lines = vec![line];
+                        for (seat, player) in seated.iter().enumerate() {
+                            if let Ok((player, owner, ready)) = players.get(*player) {
+                                let user = player.user.get(&server);
+                                let mut tags = Vec::new();
+                                if user.is_some_and(|user| bots.contains(user)) {
+                                    tags.push("bot");
+                                }
+
+                                if owner.is_some() {
+                                    tags.push("owner");
+                                }
+
+                                if ready.is_some() {
+                                    tags.push("ready");
+                                }
+
+                                lines.push(format!(
+                                    "  seat {}: {} {}",
+                                    seat,
+                                    user_name(user),
+                                    tags.join(" ")
+                                ));
+                            }
+                        }
+
+                        for spectator in watching.iter() {
+                            if let Ok(spectator) = spectators.get(*spectator) {
+                                let user = spectator.user.get(&server);
+                                lines.push(format!("  watching: {}", user_name(user)));
+                            }
+                        }
+
+                        lines.join("\n")
+                    }
+                    None => format!("no room {}", code),
+                }
+            }
+            Command::Kick(name) => match find_user(&name) {
+                Some(user_key) => {
+                    global
+                        .lobby
+                        .clear_user(&mut global.network.host(&mut server), user_key);
+                    server.user_mut(&user_key).disconnect();
+                    info!(user = user_key.to_u64(), "user kicked by admin");
+                    format!("kicked {}", name)
+                }
+                None => format!("no user {} online", name),
+            },
+            Command::Log(filter) => match log_filter.set(&filter) {
+                Ok(()) => format!("log filter set to {}", filter),
+                Err(e) => format!("invalid log filter: {}", e),
+            },
+            Command::Replay(game) => match global.storage.replay(game) {
+                Some(moves) if moves.is_empty() => format!("game {} has no moves", game),
+                Some(moves) => moves.join("\n"),
+                None => format!("no game {}", game),
+            },
+            Command::Rooms => global
+                .lobby
+                .room_summaries()
+                .filter_map(|summary| room_line(summary.room))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Shutdown(after) => {
+                match shutdown.begin(after.unwrap_or_else(|| config.shutdown_grace())) {
+                    true => String::from("shutting down"),
+                    false => String::from("already shutting down"),
+                }
+            }
+            Command::Stats(name) => {
+                let stats = global.storage.stats(&name);
+                let cards_taken = match stats.cards_taken_avg {
+                    Some(avg) => format!("{:.1} cards taken on average", avg),
+                    None => String::from("no cards taken counted"),
+                };
+
+                format!(
+                    "{}: {} games, {} as durak, {}, longest win streak {}",
+                    name, stats.games, stats.duraks, cards_taken, stats.win_streak
+                )
+            }
+            Command::Users => global
+                .lobby
+                .online()
+                .map(|user_key| {
+                    let user = global.lobby.get_user(user_key);
+                    let guest = match user.is_some_and(|user| guests.contains(user)) {
+                        true => " (guest)",
+                        false => "",
+                    };
+
+                    let place = global
+                        .lobby
+                        .room_of(user_key)
+                        .and_then(|room| rooms.get(room).ok())
+                        .map_or(String::from("lobby"), |room| format!("room {}", *room.code));
+
+                    format!("{}{} in {}", user_name(user), guest, place)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        let _ = request.reply.send(reply);
+    }
 }
 
 fn authorize(
+    config: Res<Config>,
     mut event_reader: EventReader<AuthorizationEvent<Protocol>>,
+    mut global: ResMut<Global>,
     mut server: Server<Protocol, DefaultChannels>,
+    user_names: Query<&Name, With<User>>,
 ) {
+    let global = &mut *global;
     for event in event_reader.iter() {
         if let AuthorizationEvent(user_key, Protocol::Authorize(msg)) = event {
-            if &*msg.key == obfstr!(SRV_KEY) {
-                server.accept_connection(&user_key);
-            } else {
-                server.reject_connection(&user_key);
+            let socket = server.user(user_key).address();
+            if let Some(earlier) = global.hashing.get(&socket) {
+                debug!(
+                    user = user_key.to_u64(),
+                    earlier = earlier.to_u64(),
+                    "resent handshake dropped"
+                );
+                // Nothing is connected on the address yet, so only the new user goes
+                server.user_mut(user_key).disconnect();
+                continue;
+            }
+
+            let addr = socket.ip();
+            if global.limiter.is_banned(addr) {
+                global.metrics.reject("connect", "banned");
+                server.reject_connection(user_key);
+                continue;
+            }
+
+            if let Some(token) = &*msg.token {
+                // The old connection has not timed out yet, so let the client retry later
+                if global.lobby.is_connected(token) {
+                    global.metrics.reject("connect", "connected");
+                    server.reject_connection(user_key);
+                    continue;
+                }
+
+                if global.lobby.rekey(token, *user_key) {
+                    server.accept_connection(user_key);
+                    continue;
+                }
+            }
+
+            // A resent handshake must not find its own name taken
+            let taken = |name: &str| {
+                global
+                    .logins
+                    .values()
+                    .filter(|login| login.address != socket)
+                    .any(|login| login.name.to_lowercase() == name)
+                    || global.lobby.users().any(|user| {
+                        user_names
+                            .get(user)
+                            .is_ok_and(|user_name| user_name.name.to_lowercase() == name)
+                    })
+            };
+
+            let name = match validation::name(&msg.name, taken) {
+                Ok(name) => name,
+                Err(reason) => {
+                    global.metrics.reject("name", metrics::name_reason(&reason));
+                    // Rejecting the connection could not tell the client why
+                    global.refusals.insert(*user_key, reason);
+                    server.accept_connection(user_key);
+                    continue;
+                }
+            };
+
+            let login = Login {
+                address: socket,
+                guest: msg.password.is_none(),
+                name,
+                token: (*msg.token).clone(),
+            };
+
+            // The stored hash to verify against, none for a sign-up
+            let job = match &*msg.password {
+                // Guests must not pose as the owner of an account
+                None if accounts::exists(&*global.storage, &login.name) => None,
+                None => {
+                    global.logins.insert(*user_key, login);
+                    server.accept_connection(user_key);
+                    continue;
+                }
+                Some(_) if *msg.sign_up && accounts::exists(&*global.storage, &login.name) => None,
+                Some(password) if *msg.sign_up => Some((None, password.clone())),
+                Some(password) => global
+                    .storage
+                    .profile(&login.name)
+                    .map(|profile| (Some(profile.password), password.clone())),
+            };
+
+            let Some((hash, password)) = job else {
+                global.metrics.reject("connect", "credentials");
+                server.reject_connection(user_key);
+                continue;
+            };
+
+            // Only what costs an Argon2 hash counts, guests and resumed sessions are cheap
+            if !global.limiter.attempt(addr, config.flood_ban()) {
+                global.metrics.reject("connect", "attempts");
+                server.reject_connection(user_key);
+                continue;
+            }
+
+            match hash {
+                Some(hash) => global
+                    .hasher
+                    .verify(Pending::SignIn(*user_key), hash, password),
+                None => global.hasher.hash(Pending::SignUp(*user_key), password),
+            }
+
+            // Holds the name until check_passwords accepts or rejects the user
+            global.hashing.insert(socket, *user_key);
+            global.logins.insert(*user_key, login);
+        }
+    }
+}
+
+fn chat<'world, 'state>(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    user_names: Query<&Name, With<User>>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, DefaultChannels::OrderedReliable, Protocol::SendChat(msg)) =
+            event
+        {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            let name = match global.lobby.get_user(*user_key) {
+                Some(user) => match user_names.get(user) {
+                    Ok(name) => (*name.name).clone(),
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+
+            let text = validation::chat(&msg.text).to_string();
+            if text.is_empty() {
+                continue;
+            }
+
+            let recipients = match *msg.room {
+                true => global.lobby.room_mates(*user_key),
+                false => global.lobby.online().collect(),
+            };
+
+            let chat = Chat::new(name, *msg.room, text);
+            for recipient in recipients {
+                server.send_message(&recipient, DefaultChannels::OrderedReliable, &chat);
+            }
+        }
+    }
+}
+
+fn check_passwords(
+    mut global: ResMut<Global>,
+    mut server: Server<Protocol, DefaultChannels>,
+    shutdown: Res<Shutdown>,
+    user_names: Query<&Name, With<User>>,
+) {
+    let global = &mut *global;
+    for (pending, outcome) in global.hasher.outcomes() {
+        match (pending, outcome) {
+            (
+                Pending::CreateRoom {
+                    private,
+                    user_key,
+                    variant,
+                },
+                Outcome::Hashed(hash),
+            ) => {
+                if refuse_room(global, &mut server, &shutdown, user_key, "create") {
+                    continue;
+                }
+
+                match hash {
+                    Some(hash) => create_room(
+                        global,
+                        &mut server,
+                        &user_names,
+                        user_key,
+                        Some(hash),
+                        private,
+                        variant,
+                    ),
+                    // A room without its password would let anyone in
+                    None => {
+                        global.metrics.reject("create", "failed");
+                        server.send_message(
+                            &user_key,
+                            DefaultChannels::UnorderedReliable,
+                            &JoinRejected::new(Rejection::Failed),
+                        );
+                    }
+                }
+            }
+            (
+                Pending::EnterRoom {
+                    room,
+                    user_key,
+                    watch,
+                },
+                Outcome::Verified(unlocked),
+            ) => enter(
+                global,
+                &mut server,
+                &user_names,
+                user_key,
+                Some(room),
+                watch,
+                unlocked,
+            ),
+            (Pending::SignIn(user_key), Outcome::Verified(accepted)) => {
+                finish_login(global, &mut server, user_key, accepted)
+            }
+            (Pending::SignUp(user_key), Outcome::Hashed(hash)) => {
+                let accepted = match (hash, global.logins.get(&user_key)) {
+                    (Some(hash), Some(login)) => {
+                        accounts::sign_up(&mut *global.storage, &login.name, hash)
+                    }
+                    _ => false,
+                };
+
+                finish_login(global, &mut server, user_key, accepted);
+            }
+            _ => unreachable!("hash jobs come back hashed and verify jobs verified"),
+        }
+    }
+}
+
+fn connect<'world, 'state>(
+    config: Res<Config>,
+    mut events: EventReader<ConnectionEvent>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+) {
+    let global = &mut *global;
+    for event in events.iter() {
+        let ConnectionEvent(user_key) = event;
+        if let Some(reason) = global.refusals.remove(user_key) {
+            server.send_message(
+                user_key,
+                DefaultChannels::UnorderedReliable,
+                &NameRejected::new(reason),
+            );
+            continue;
+        }
+
+        let resumed = global
+            .lobby
+            .resume(&mut global.network.host(&mut server), *user_key);
+        if let Some((user, token, room)) = resumed {
+            info!(user = user_key.to_u64(), room = ?room, "user resumed");
+            let mut own = OwnUser::new(config.tick, token);
+            own.user.set(&server, &user);
+            if let Some(room) = room {
+                own.room.set(&server, &room);
             }
+
+            server.send_message(user_key, DefaultChannels::UnorderedReliable, &own);
+            continue;
         }
+
+        if let Some(login) = global.logins.remove(user_key) {
+            let identity = match login.guest {
+                true => Identity::Address(server.user(user_key).address().ip()),
+                false => Identity::Account(login.name.to_lowercase()),
+            };
+            let (user, token) =
+                global
+                    .lobby
+                    .register(&mut global.network.host(&mut server), *user_key, identity);
+            server
+                .entity_mut(&user)
+                .insert(Name::new(login.name.clone()))
+                .insert(User::new());
+
+            if login.guest {
+                server.entity_mut(&user).insert(Guest::new());
+            }
+
+            info!(
+                user = user_key.to_u64(),
+                name = %login.name,
+                guest = login.guest,
+                "user registered"
+            );
+            let mut own = OwnUser::new(config.tick, token);
+            own.user.set(&server, &user);
+            let reclaim = login.token.as_deref().and_then(|token| {
+                global.lobby.reclaim(
+                    &mut global.network.host(&mut server),
+                    *user_key,
+                    &login.name,
+                    token,
+                )
+            });
+
+            if let Some(reclaim) = reclaim {
+                info!(user = user_key.to_u64(), room = ?reclaim.room, "back in a restored game");
+                let mut player = Player::new();
+                player.room.set(&server, &reclaim.room);
+                player.user.set(&server, &user);
+                server.entity_mut(&reclaim.player).insert(player);
+                own.room.set(&server, &reclaim.room);
+
+                if let Some(code) = reclaim.code {
+                    server
+                        .entity_mut(&reclaim.room)
+                        .insert(Name::new(login.name.clone()))
+                        .insert(Room::new(code, reclaim.locked));
+                }
+
+                for (i, entity) in reclaim.bots.iter().enumerate() {
+                    let mut player = Player::new();
+                    player.room.set(&server, &reclaim.room);
+                    player.user.set(&server, entity);
+                    server
+                        .entity_mut(entity)
+                        .insert(player)
+                        .insert(Bot::new())
+                        .insert(Name::new(format!("{} {}", BOT_NAME, i + 1)));
+                }
+            }
+
+            server.send_message(user_key, DefaultChannels::UnorderedReliable, &own);
+        }
+    }
+}
+
+fn create_room(
+    global: &mut Global,
+    server: &mut Server<Protocol, DefaultChannels>,
+    user_names: &Query<&Name, With<User>>,
+    user_key: UserKey,
+    password: Option<String>,
+    private: bool,
+    variant: Variant,
+) {
+    let user_entity = match global.lobby.get_user(user_key) {
+        Some(user_entity) => user_entity,
+        None => return,
+    };
+
+    let name = match user_names.get(user_entity) {
+        Ok(name) => name,
+        Err(_) => return,
+    };
+
+    let locked = password.is_some();
+    let (player_entity, room_entity, code) = global.lobby.spawn_room(
+        &mut global.network.host(server),
+        user_key,
+        &name.name,
+        password,
+        private,
+        Rules::new(variant),
+    );
+    info!(
+        user = user_key.to_u64(),
+        room = ?room_entity,
+        %code,
+        locked,
+        private,
+        "room created"
+    );
+    let mut player = Player::new();
+    player.room.set(server, &room_entity);
+    player.user.set(server, &user_entity);
+
+    server.entity_mut(&player_entity).insert(player);
+
+    server
+        .entity_mut(&room_entity)
+        .insert(name.clone())
+        .insert(Room::new(code, locked));
+}
+
+fn disconnect(mut events: EventReader<DisconnectionEvent>, mut global: ResMut<Global>) {
+    for event in events.iter() {
+        let DisconnectionEvent(user_key, _) = event;
+        info!(user = user_key.to_u64(), "user disconnected");
+        global.logins.remove(user_key);
+        global.limiter.forget(*user_key);
+        global.lobby.orphan(*user_key);
+        global.refusals.remove(user_key);
     }
 }
 
-fn debug<'world, 'state>(
-    others: Query<Entity, (Without<Player>, Without<Room>, Without<User>)>,
+#[allow(clippy::too_many_arguments)]
+fn dump<'world, 'state>(
+    bots: Query<&Name, With<Bot>>,
+    console: Res<Console>,
+    others: Query<Entity, Loose>,
     owners: Query<&Owner>,
     players: Query<(Entity, &Player)>,
     rooms: Query<(Entity, &Name), With<Room>>,
     server: Server<'world, 'state, Protocol, DefaultChannels>,
     users: Query<(Entity, &Name), With<User>>,
 ) {
+    if !console.take_dump() {
+        return;
+    }
+
     for (i, room_key) in server.room_keys().iter().enumerate() {
+        let room = server.room(room_key);
         info!(
-            "{} (entities:{}, users:{}):",
-            i,
-            server.room(&room_key).entities_count(),
-            server.room(&room_key).users_count(),
+            naia_room = i,
+            entities = room.entities_count(),
+            users = room.users_count(),
+            "naia room"
         );
 
         for entity in others.iter() {
-            if server.room(&room_key).has_entity(&entity) {
-                info!("  other");
+            if room.has_entity(&entity) {
+                info!(naia_room = i, entity = ?entity, "other entity");
             }
         }
 
         for (entity, player) in players.iter() {
-            if server.room(&room_key).has_entity(&entity) {
-                let user_entity = player.user.get(&server).unwrap();
-                let (_, user) = users.get(user_entity).unwrap();
-                if owners.get(entity).is_ok() {
-                    info!("  player: {} (owner)", *user.name);
-                } else {
-                    info!("  player: {}", *user.name);
-                }
+            if !room.has_entity(&entity) {
+                continue;
+            }
+
+            let user = match player.user.get(&server) {
+                Some(user) => user,
+                None => continue,
+            };
+
+            if let Ok(bot) = bots.get(user) {
+                info!(naia_room = i, name = %bot.name.as_str(), bot = true, "player");
+            } else if let Ok((_, name)) = users.get(user) {
+                let owner = owners.contains(entity);
+                info!(naia_room = i, name = %name.name.as_str(), owner, "player");
             }
         }
 
-        for (entity, room) in rooms.iter() {
-            if server.room(&room_key).has_entity(&entity) {
-                info!("  room: {}", *room.name);
+        for (entity, name) in rooms.iter() {
+            if room.has_entity(&entity) {
+                info!(naia_room = i, name = %name.name.as_str(), "room");
             }
         }
 
-        for (entity, user) in users.iter() {
-            if server.room(&room_key).has_entity(&entity) {
-                info!("  user: {}", *user.name);
+        for (entity, name) in users.iter() {
+            if room.has_entity(&entity) {
+                info!(naia_room = i, name = %name.name.as_str(), "user");
             }
         }
     }
 }
 
-fn disconnect<'world, 'state>(
-    mut events: EventReader<DisconnectionEvent>,
+/// Seats the user in `room`, or lets it watch, and tells the client why if it may not.
+fn enter(
+    global: &mut Global,
+    server: &mut Server<Protocol, DefaultChannels>,
+    user_names: &Query<&Name, With<User>>,
+    user_key: UserKey,
+    room: Option<Entity>,
+    watch: bool,
+    unlocked: bool,
+) {
+    let user = match global.lobby.get_user(user_key) {
+        Some(user) => user,
+        None => return,
+    };
+
+    let name = match user_names.get(user) {
+        Ok(name) => name.name.as_str(),
+        Err(_) => return,
+    };
+
+    let res = room.ok_or(Rejection::NotFound).and_then(|room| {
+        let host = &mut global.network.host(server);
+        match watch {
+            true => global.lobby.watch_room(host, room, user_key, unlocked),
+            false => global
+                .lobby
+                .enter_room(host, room, user_key, name, unlocked),
+        }
+        .map(|entity| (entity, room))
+    });
+
+    match res {
+        Ok((entity, room)) if watch => {
+            info!(user = user_key.to_u64(), room = ?room, "watching room");
+            let mut spectator = Spectator::new();
+            spectator.room.set(server, &room);
+            spectator.user.set(server, &user);
+            server.entity_mut(&entity).insert(spectator);
+        }
+        Ok((entity, room)) => {
+            info!(user = user_key.to_u64(), room = ?room, "joined room");
+            let mut player = Player::new();
+            player.room.set(server, &room);
+            player.user.set(server, &user);
+            server.entity_mut(&entity).insert(player);
+        }
+        Err(reason) => {
+            let action = match watch {
+                true => "watch",
+                false => "join",
+            };
+
+            debug!(user = user_key.to_u64(), action, "entering rejected");
+            global.metrics.reject(action, metrics::join_reason(&reason));
+            server.send_message(
+                &user_key,
+                DefaultChannels::UnorderedReliable,
+                &JoinRejected::new(reason),
+            );
+        }
+    }
+}
+
+fn enter_room<'world, 'state>(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
     mut global: ResMut<Global>,
-    players: Query<&Player>,
-    mut room_names: Query<&mut Name, (With<Room>, Without<User>)>,
+    rooms: Query<&Room>,
     mut server: Server<'world, 'state, Protocol, DefaultChannels>,
-    user_names: Query<&Name, (With<User>, Without<Room>)>,
+    user_names: Query<&Name, With<User>>,
 ) {
+    let global = &mut *global;
     for event in events.iter() {
-        let DisconnectionEvent(user_key, _) = event;
-        if let Some((room, successor)) = global.lobby.clear_user(&mut server, *user_key) {
-            server.entity_mut(&successor).insert(Owner::new());
-            if let Ok(player) = players.get(successor) {
-                if let Ok(mut room_name) = room_names.get_mut(room) {
-                    if let Some(user) = player.user.get(&server) {
-                        if let Ok(user_name) = user_names.get(user) {
-                            *room_name.name = (*user_name.name).clone();
-                        }
-                    }
-                }
+        if let MessageEvent(user_key, _, Protocol::JoinRoom(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
             }
+
+            let room = match &*msg.code {
+                Some(code) => global.lobby.code_room(code),
+                // Clients may send any entity they know of
+                None => msg.room.get(&server).filter(|room| rooms.contains(*room)),
+            };
+
+            unlock(
+                global,
+                &mut server,
+                &user_names,
+                *user_key,
+                room,
+                false,
+                (*msg.password).as_deref(),
+            );
         }
     }
 }
 
-fn enter_room<'world, 'state>(
+fn expire<'world, 'state>(
+    config: Res<Config>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    shutdown: Res<Shutdown>,
+) {
+    let global = &mut *global;
+    let restoring = global.lobby.has_saved();
+    global.lobby.forget_saved(config.resume_grace());
+    // A shutdown writes the snapshot anew, which must stay
+    if restoring && !global.lobby.has_saved() && !shutdown.is_pending() {
+        if let Some(path) = &config.snapshot {
+            info!(path = %path.display(), "restored games settled");
+            shutdown::discard(path);
+        }
+    }
+
+    for user_key in global.lobby.expired(config.resume_grace()) {
+        info!(user = user_key.to_u64(), "session expired");
+        global
+            .lobby
+            .clear_user(&mut global.network.host(&mut server), user_key);
+    }
+}
+
+fn finish_login(
+    global: &mut Global,
+    server: &mut Server<Protocol, DefaultChannels>,
+    user_key: UserKey,
+    accepted: bool,
+) {
+    global.hashing.retain(|_, pending| *pending != user_key);
+    // The user may have timed out while waiting
+    if !accepted || !server.user_exists(&user_key) {
+        global.logins.remove(&user_key);
+    }
+
+    match accepted {
+        true => server.accept_connection(&user_key),
+        false => {
+            global.metrics.reject("connect", "credentials");
+            server.reject_connection(&user_key);
+        }
+    }
+}
+
+fn idle<'world, 'state>(
+    config: Res<Config>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+) {
+    let global = &mut *global;
+    for user_key in std::mem::take(&mut global.idle_kicks) {
+        if server.user_exists(&user_key) {
+            server.user_mut(&user_key).disconnect();
+        }
+    }
+
+    let idlers = global.lobby.idle(
+        &mut global.network.host(&mut server),
+        config.idle_warn(),
+        config.idle_timeout(),
+    );
+
+    for (user_key, idle) in idlers {
+        let notice = match idle {
+            Idle::Disconnect => {
+                info!(user = user_key.to_u64(), "disconnecting idle user");
+                // Resuming would only bring the user back to idle on
+                global
+                    .lobby
+                    .clear_user(&mut global.network.host(&mut server), user_key);
+                global.idle_kicks.push(user_key);
+                IdleNotice::new(Idleness::Disconnected, 0)
+            }
+            Idle::Moved => IdleNotice::new(Idleness::Moved, 0),
+            Idle::Warning(left) => IdleNotice::new(Idleness::Warning, left.as_secs() as u16),
+        };
+
+        server.send_message(&user_key, DefaultChannels::UnorderedReliable, &notice);
+    }
+
+    let closed = global
+        .lobby
+        .close_stale(&mut global.network.host(&mut server), config.room_timeout());
+    for user_key in closed {
+        debug!(user = user_key.to_u64(), "stale room closed");
+        server.send_message(
+            &user_key,
+            DefaultChannels::UnorderedReliable,
+            &IdleNotice::new(Idleness::Closed, 0),
+        );
+    }
+}
+
+fn kick_player<'world, 'state>(
     mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
     mut global: ResMut<Global>,
     mut server: Server<'world, 'state, Protocol, DefaultChannels>,
 ) {
     let global = &mut *global;
     for event in events.iter() {
-        if let MessageEvent(user_key, _, Protocol::JoinRoom(msg)) = event {
+        if let MessageEvent(user_key, _, Protocol::KickPlayer(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            let player = match msg.player.get(&server) {
+                Some(player) => player,
+                None => continue,
+            };
+
+            let kicked = global.lobby.kick(
+                &mut global.network.host(&mut server),
+                *user_key,
+                player,
+                *msg.ban,
+            );
+
+            match kicked {
+                Ok(target) => {
+                    info!(
+                        user = user_key.to_u64(),
+                        target = target.to_u64(),
+                        ban = *msg.ban,
+                        "player kicked"
+                    );
+                    server.send_message(
+                        &target,
+                        DefaultChannels::UnorderedReliable,
+                        &Kicked::new(*msg.ban),
+                    );
+                }
+                Err(reason) => global.metrics.reject("kick", metrics::join_reason(&reason)),
+            }
+        }
+    }
+}
+
+fn leaderboard<'world, 'state>(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    guests: Query<&Guest>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    user_names: Query<&Name, With<User>>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::RequestLeaderboard(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
             if let Some(user) = global.lobby.get_user(*user_key) {
-                if let Some(room) = msg.room.get(&server) {
-                    if let Some(entity) = global.lobby.enter_room(room, &mut server, *user_key) {
-                        let mut player = Player::new();
-                        player.user.set(&server, &user);
-                        server.entity_mut(&entity).insert(player);
-                    }
+                let (rank, rating) = match user_names.get(user) {
+                    Ok(name) if !guests.contains(user) => (
+                        global.storage.rank(&name.name),
+                        global
+                            .storage
+                            .profile(&name.name)
+                            .map(|profile| profile.rating.round() as u16),
+                    ),
+                    _ => (None, None),
+                };
+
+                let leaderboard = global
+                    .storage
+                    .leaderboard((*msg.count).min(LEADERBOARD_MAX) as usize);
+                let mut pages = leaderboard.chunks(LEADERBOARD_PAGE).collect::<Vec<_>>();
+                if pages.is_empty() {
+                    pages.push(&[]);
+                }
+
+                for (i, page) in pages.into_iter().enumerate() {
+                    let (names, ratings) = page
+                        .iter()
+                        .map(|(name, rating)| (name.clone(), rating.round() as u16))
+                        .unzip();
+
+                    server.send_message(
+                        user_key,
+                        DefaultChannels::UnorderedReliable,
+                        &Leaderboard::new(
+                            (i * LEADERBOARD_PAGE) as u8,
+                            names,
+                            rank,
+                            rating,
+                            ratings,
+                        ),
+                    );
                 }
             }
         }
@@ -154,117 +1094,721 @@ fn enter_room<'world, 'state>(
 fn leave_room<'world, 'state>(
     mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
     mut global: ResMut<Global>,
-    players: Query<&Player>,
-    mut room_names: Query<&mut Name, (With<Room>, Without<User>)>,
     mut server: Server<'world, 'state, Protocol, DefaultChannels>,
-    user_names: Query<&Name, (With<User>, Without<Room>)>,
 ) {
     let global = &mut *global;
     for event in events.iter() {
         if let MessageEvent(user_key, _, Protocol::LeaveRoom(_)) = event {
-            if let Some((room, successor)) = global.lobby.leave_room(&mut server, *user_key) {
-                server.entity_mut(&successor).insert(Owner::new());
-                if let Ok(player) = players.get(successor) {
-                    if let Ok(mut room_name) = room_names.get_mut(room) {
-                        if let Some(user) = player.user.get(&server) {
-                            if let Ok(user_name) = user_names.get(user) {
-                                *room_name.name = (*user_name.name).clone();
-                            }
-                        }
-                    }
-                }
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
             }
+
+            if let Some(room) = global.lobby.room_of(*user_key) {
+                info!(user = user_key.to_u64(), room = ?room, "left room");
+            }
+
+            global
+                .lobby
+                .leave_room(&mut global.network.host(&mut server), *user_key);
         }
     }
 }
 
 fn main() {
+    let config = Config::load();
+    let log_filter = LogFilter::init(&config.log_level, config.log_json);
+    let mut server_config = ServerConfig::default();
+    if config.metrics_addr.is_some() {
+        server_config.connection.bandwidth_measure_duration = Some(BANDWIDTH_WINDOW);
+    }
+
+    let shared_config = protocol::shared_config(config.tick());
     App::new()
+        .insert_resource(config)
+        .insert_resource(log_filter)
         .add_plugin(CorePlugin::default())
         .add_plugin(ScheduleRunnerPlugin::default())
-        .add_plugin(LogPlugin::default())
         .add_plugin(ServerPlugin::<Protocol, DefaultChannels>::new(
-            ServerConfig::default(),
-            SharedConfig::default(),
+            server_config,
+            shared_config,
         ))
         .add_startup_system(setup)
+        .add_system_to_stage(CoreStage::First, start_frame)
         .add_system_to_stage(Stage::ReceiveEvents, authorize)
+        .add_system_to_stage(Stage::ReceiveEvents, chat)
+        .add_system_to_stage(Stage::ReceiveEvents, connect)
         .add_system_to_stage(Stage::ReceiveEvents, disconnect)
         .add_system_to_stage(Stage::ReceiveEvents, enter_room)
+        .add_system_to_stage(Stage::ReceiveEvents, kick_player)
+        .add_system_to_stage(Stage::ReceiveEvents, leaderboard)
         .add_system_to_stage(Stage::ReceiveEvents, leave_room)
-        .add_system_to_stage(Stage::ReceiveEvents, register)
+        .add_system_to_stage(Stage::ReceiveEvents, move_seat)
+        .add_system_to_stage(Stage::ReceiveEvents, quick_play)
+        .add_system_to_stage(Stage::ReceiveEvents, set_ready)
         .add_system_to_stage(Stage::ReceiveEvents, spawn_room)
-        .add_system_to_stage(Stage::Tick, debug)
-        .add_system_to_stage(Stage::Tick, update_scope.after(debug))
-        .add_system_to_stage(Stage::Tick, update_server.after(update_scope))
+        .add_system_to_stage(Stage::ReceiveEvents, start_game)
+        .add_system_to_stage(Stage::ReceiveEvents, touch)
+        .add_system_to_stage(Stage::ReceiveEvents, transfer_ownership)
+        .add_system_to_stage(Stage::ReceiveEvents, watch_room)
+        .add_system_to_stage(Stage::Tick, admin)
+        .add_system_to_stage(Stage::Tick, check_passwords)
+        .add_system_to_stage(Stage::Tick, dump)
+        .add_system_to_stage(Stage::Tick, expire)
+        .add_system_to_stage(Stage::Tick, idle)
+        .add_system_to_stage(Stage::Tick, match_queue)
+        .add_system_to_stage(Stage::Tick, metrics.after(update_server))
+        .add_system_to_stage(Stage::Tick, punish)
+        .add_system_to_stage(Stage::Tick, record)
+        .add_system_to_stage(Stage::Tick, shutdown)
+        .add_system_to_stage(Stage::Tick, update_online)
+        .add_system_to_stage(Stage::Tick, update_rooms.after(match_queue))
+        .add_system_to_stage(Stage::Tick, update_seats.after(match_queue))
+        .add_system_to_stage(Stage::Tick, update_scope.after(dump))
+        .add_system_to_stage(
+            Stage::Tick,
+            update_server
+                .after(update_online)
+                .after(update_rooms)
+                .after(update_scope)
+                .after(update_seats),
+        )
         .run();
 }
 
-fn register<'world, 'state>(
+fn match_queue<'world, 'state>(
+    config: Res<Config>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    shutdown: Res<Shutdown>,
+    user_names: Query<&Name, With<User>>,
+) {
+    if shutdown.is_pending() {
+        return;
+    }
+
+    let global = &mut *global;
+    let matches = global
+        .lobby
+        .match_queue(&mut global.network.host(&mut server), config.bot_wait());
+    for quick in matches {
+        info!(
+            room = ?quick.room,
+            code = %quick.code,
+            players = quick.players.len(),
+            bots = quick.bots.len(),
+            "quick match"
+        );
+        for (i, (entity, user_key)) in quick.players.iter().enumerate() {
+            if let Some(user) = global.lobby.get_user(*user_key) {
+                let mut player = Player::new();
+                player.room.set(&server, &quick.room);
+                player.user.set(&server, &user);
+                server.entity_mut(entity).insert(player);
+
+                if i == 0 {
+                    if let Ok(name) = user_names.get(user) {
+                        server
+                            .entity_mut(&quick.room)
+                            .insert(name.clone())
+                            .insert(Room::new(quick.code.clone(), false));
+                    }
+                }
+            }
+        }
+
+        // Bots name themselves
+        for (i, entity) in quick.bots.iter().enumerate() {
+            let mut player = Player::new();
+            player.room.set(&server, &quick.room);
+            player.user.set(&server, entity);
+            server
+                .entity_mut(entity)
+                .insert(player)
+                .insert(Bot::new())
+                .insert(Name::new(format!("{} {}", BOT_NAME, i + 1)));
+        }
+    }
+}
+
+fn metrics(
     mut global: ResMut<Global>,
+    names: Query<&Name, With<User>>,
+    mut naia: ResMut<NaiaServer<Protocol, Entity, DefaultChannels>>,
+) {
+    let global = &mut *global;
+    global.metrics.tick();
+    if !global.metrics.is_stale() {
+        return;
+    }
+
+    // The bevy wrapper has no bandwidth getters, so naia is read directly. Running after
+    // `update_server` keeps this apart from every system using the wrapper.
+    let mut sent = Vec::new();
+    for user_key in global.lobby.online() {
+        let name = global
+            .lobby
+            .get_user(user_key)
+            .and_then(|user| names.get(user).ok());
+        if let (Some(name), true) = (name, naia.user_exists(&user_key)) {
+            let addr = naia.user(&user_key).address();
+            // naia measures kilobits per second
+            let kbps = naia.outgoing_bandwidth_to_client(&addr);
+            sent.push(((*name.name).clone(), kbps * 125.0));
+        }
+    }
+
+    let gauges = Gauges {
+        games: global
+            .lobby
+            .room_summaries()
+            .filter(|summary| *summary.status == RoomStatus::InGame)
+            .count(),
+        rooms: global.lobby.room_summaries().count(),
+        sent,
+        users: global.lobby.online().count(),
+    };
+
+    global.metrics.render(gauges, global.limiter.dropped());
+}
+
+fn move_seat(
     mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    server: Server<Protocol, DefaultChannels>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::MoveSeat(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            if !global.lobby.is_owner(*user_key) {
+                continue;
+            }
+
+            if let Some(player) = msg.player.get(&server) {
+                if global
+                    .lobby
+                    .move_seat(*user_key, player, *msg.seat as usize)
+                {
+                    debug!(user = user_key.to_u64(), player = ?player, seat = *msg.seat, "seat moved");
+                }
+            }
+        }
+    }
+}
+
+fn punish<'world, 'state>(
+    config: Res<Config>,
+    mut global: ResMut<Global>,
     mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+) {
+    for user_key in global.limiter.offenders() {
+        if !server.user_exists(&user_key) {
+            continue;
+        }
+
+        let addr = server.user(&user_key).address().ip();
+        warn!(user = user_key.to_u64(), %addr, "disconnecting user for flooding");
+        global.limiter.ban(addr, config.flood_ban());
+        server.user_mut(&user_key).disconnect();
+    }
+}
+
+fn quick_play<'world, 'state>(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    shutdown: Res<Shutdown>,
+    user_names: Query<&Name, With<User>>,
 ) {
     for event in events.iter() {
-        if let MessageEvent(user_key, _, Protocol::RegisterUser(msg)) = event {
-            let user = &global.lobby.register(&mut server, *user_key);
-            server
-                .entity_mut(user)
-                .insert(Name::new((*msg.name).clone()))
-                .insert(User::new());
+        if let MessageEvent(user_key, _, Protocol::QuickPlay(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
 
-            let mut own = OwnUser::new();
-            own.user.set(&server, user);
-            server.send_message(user_key, DefaultChannels::UnorderedReliable, &own);
+            if refuse_room(&mut global, &mut server, &shutdown, *user_key, "quick_play") {
+                continue;
+            }
+
+            if let Some(user) = global.lobby.get_user(*user_key) {
+                if let Ok(name) = user_names.get(user) {
+                    debug!(user = user_key.to_u64(), "queued for quick play");
+                    global.lobby.queue(
+                        *user_key,
+                        &name.name,
+                        (*msg.players).map(|players| players as usize),
+                        (*msg.variant).clone(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn record(mut global: ResMut<Global>) {
+    for game in global.lobby.take_finished() {
+        if let Some(id) = rating::record(&mut *global.storage, game) {
+            info!(game = id, "game recorded");
         }
     }
 }
 
-fn setup(mut commands: Commands, mut server: Server<Protocol, DefaultChannels>) {
+fn refuse_room(
+    global: &mut Global,
+    server: &mut Server<Protocol, DefaultChannels>,
+    shutdown: &Shutdown,
+    user_key: UserKey,
+    action: &'static str,
+) -> bool {
+    let left = match shutdown.remaining() {
+        Some(left) => left,
+        None => return false,
+    };
+
+    global.metrics.reject(action, "shutdown");
+    server.send_message(
+        &user_key,
+        DefaultChannels::UnorderedReliable,
+        &ShutdownNotice::new(left.as_secs() as u16),
+    );
+    true
+}
+
+fn set_ready(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::SetReady(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            if global.lobby.set_ready(*user_key, *msg.ready) {
+                debug!(user = user_key.to_u64(), ready = *msg.ready, "set ready");
+            }
+        }
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    config: Res<Config>,
+    mut server: Server<Protocol, DefaultChannels>,
+) {
     server.listen(&ServerAddrs::new(
-        format!("{}:{}", SRV_ADDR, SRV_PORT).parse().unwrap(),
-        format!("{}:{}", SRV_ADDR, SRV_PORT_WRTC).parse().unwrap(),
-        &format!("{}://{}:{}", SRV_PROT, SRV_ADDR_PUB, SRV_PORT_WRTC),
+        config.addr_session(),
+        config.addr_wrtc(),
+        &config.url_pub(),
     ));
 
+    let storage: Box<dyn Storage> = match &config.database {
+        Some(path) => Box::new(SqliteStorage::open(path).expect("failed to open database")),
+        None => Box::new(MemoryStorage::new()),
+    };
+
+    commands.insert_resource(Console::start(config.admin_socket.as_deref()));
+    commands.insert_resource(Shutdown::new());
+    let mut lobby = Lobby::new();
+    if let Some(path) = &config.snapshot {
+        let rooms = shutdown::load(path);
+        if !rooms.is_empty() {
+            info!(games = rooms.len(), path = %path.display(), "restoring games");
+            lobby.restore(rooms);
+        }
+    }
+
+    let network = Network::new(&mut server);
+    let online = server
+        .spawn()
+        .enter_room(&network.lobby_key)
+        .insert(Online::new())
+        .id();
+
     commands.insert_resource(Global {
-        lobby: Lobby::new(server.make_room().key()),
+        hasher: Hasher::start(),
+        hashing: HashMap::new(),
+        idle_kicks: Vec::new(),
+        limiter: Limiter::new(config.login_attempts),
+        lobby,
+        logins: HashMap::new(),
+        metrics: Metrics::new(config.metrics_addr),
+        network,
+        online,
+        refusals: HashMap::new(),
+        storage,
     });
 }
 
+fn shutdown<'world, 'state>(
+    config: Res<Config>,
+    mut exit: EventWriter<AppExit>,
+    global: Res<Global>,
+    names: Query<&Name, With<User>>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    mut shutdown: ResMut<Shutdown>,
+) {
+    if shutdown.take_signal() && shutdown.begin(config.shutdown_grace()) {
+        info!("received SIGTERM");
+    }
+
+    // Leaves naia a tick to send the disconnects
+    if shutdown.is_finished() {
+        exit.send(AppExit);
+        return;
+    }
+
+    if let Some(seconds) = shutdown.notice() {
+        info!(seconds, "shutdown notice");
+        let notice = ShutdownNotice::new(seconds.min(u16::MAX as u64) as u16);
+        for user_key in global.lobby.online() {
+            server.send_message(&user_key, DefaultChannels::UnorderedReliable, &notice);
+        }
+    }
+
+    if !shutdown.is_due() {
+        return;
+    }
+
+    if let Some(path) = &config.snapshot {
+        let rooms = global.lobby.snapshot(|user_key| {
+            global
+                .lobby
+                .get_user(user_key)
+                .and_then(|user| names.get(user).ok())
+                .map(|name| (*name.name).clone())
+        });
+
+        match shutdown::save(path, &rooms) {
+            Ok(()) => info!(games = rooms.len(), path = %path.display(), "saved games"),
+            Err(e) => error!("failed to save games to {}: {}", path.display(), e),
+        }
+    }
+
+    for user_key in global.lobby.online().collect::<Vec<_>>() {
+        server.user_mut(&user_key).disconnect();
+    }
+
+    shutdown.finish();
+}
+
 fn spawn_room<'world, 'state>(
     mut global: ResMut<Global>,
     mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
-    names: Query<&Name>,
     mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    shutdown: Res<Shutdown>,
+    user_names: Query<&Name, With<User>>,
 ) {
+    let global = &mut *global;
     for event in events.iter() {
-        if let MessageEvent(user_key, _, Protocol::CreateRoom(_)) = event {
-            if let Some(user_entity) = global.lobby.get_user(*user_key) {
-                let (player_entity, room_entity) = global.lobby.spawn_room(&mut server, *user_key);
-                let mut player = Player::new();
-                player.user.set(&server, &user_entity);
+        if let MessageEvent(user_key, _, Protocol::CreateRoom(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            if refuse_room(global, &mut server, &shutdown, *user_key, "create") {
+                continue;
+            }
+
+            let private = *msg.private;
+            let variant = (*msg.variant).clone();
+            match (*msg.password).clone() {
+                Some(password) if !password.is_empty() => global.hasher.hash(
+                    Pending::CreateRoom {
+                        private,
+                        user_key: *user_key,
+                        variant,
+                    },
+                    password,
+                ),
+                _ => create_room(
+                    global,
+                    &mut server,
+                    &user_names,
+                    *user_key,
+                    None,
+                    private,
+                    variant,
+                ),
+            }
+        }
+    }
+}
+
+// `CoreStage::First` runs before the startup systems, so there is no `Global` on the first frame
+fn start_frame(global: Option<ResMut<Global>>) {
+    if let Some(mut global) = global {
+        global.metrics.start_frame();
+    }
+}
+
+fn start_game(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::StartGame(_)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            if !global.lobby.is_owner(*user_key) {
+                global.metrics.reject("start", "not_owner");
+                continue;
+            }
 
-                server
-                    .entity_mut(&player_entity)
-                    .insert(player)
-                    .insert(Owner::new());
+            match global.lobby.start_game(*user_key) {
+                true => {
+                    info!(user = user_key.to_u64(), room = ?global.lobby.room_of(*user_key), "game started")
+                }
+                false => global.metrics.reject("start", "not_ready"),
+            }
+        }
+    }
+}
 
-                server
-                    .entity_mut(&room_entity)
-                    .insert(names.get(user_entity).unwrap().clone())
-                    .insert(Room::new());
+/// Any message counts as activity.
+fn touch(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+) {
+    for MessageEvent(user_key, _, msg) in events.iter() {
+        global.lobby.touch(*user_key);
+        global.metrics.message(flood::message_kind(msg));
+    }
+}
+
+fn transfer_ownership(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    server: Server<Protocol, DefaultChannels>,
+) {
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::TransferOwnership(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            if !global.lobby.is_owner(*user_key) {
+                continue;
             }
+
+            if let Some(successor) = msg.player.get(&server) {
+                if global.lobby.transfer(*user_key, successor) {
+                    debug!(user = user_key.to_u64(), player = ?successor, "ownership transferred");
+                }
+            }
+        }
+    }
+}
+
+/// Leaves checking the password of a locked room to the hasher.
+fn unlock(
+    global: &mut Global,
+    server: &mut Server<Protocol, DefaultChannels>,
+    user_names: &Query<&Name, With<User>>,
+    user_key: UserKey,
+    room: Option<Entity>,
+    watch: bool,
+    password: Option<&str>,
+) {
+    let hash = room.and_then(|room| global.lobby.password(room));
+    match (room, hash, password) {
+        (Some(room), Some(hash), Some(password)) => global.hasher.verify(
+            Pending::EnterRoom {
+                room,
+                user_key,
+                watch,
+            },
+            hash.to_string(),
+            password.to_string(),
+        ),
+        _ => enter(global, server, user_names, user_key, room, watch, false),
+    }
+}
+
+fn update_online(global: Res<Global>, mut online: Query<&mut Online>) {
+    if let Ok(mut online) = online.get_mut(global.online) {
+        let users = global.lobby.online().count() as u16;
+        if *online.users != users {
+            *online.users = users;
         }
     }
 }
 
-fn update_scope(mut server: Server<Protocol, DefaultChannels>) {
+/// Rooms are named after their owner.
+fn update_rooms<'world, 'state>(
+    global: Res<Global>,
+    players: Query<&Player>,
+    mut rooms: Query<(&mut Name, &mut Room, Option<&mut RoomSummary>), Without<User>>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    user_names: Query<&Name, (With<User>, Without<Room>)>,
+) {
+    for summary in global.lobby.room_summaries() {
+        let owner_name = summary
+            .owner
+            .and_then(|owner| players.get(owner).ok())
+            .and_then(|player| player.user.get(&server))
+            .and_then(|user| user_names.get(user).ok());
+
+        if let Ok((mut name, mut room, room_summary)) = rooms.get_mut(summary.room) {
+            if let Some(owner_name) = owner_name {
+                if *name.name != *owner_name.name {
+                    *name.name = (*owner_name.name).clone();
+                }
+            }
+
+            if *room.status != *summary.status {
+                *room.status = summary.status.clone();
+            }
+
+            match room_summary {
+                Some(mut room_summary) => {
+                    if *room_summary.players as usize != summary.players {
+                        *room_summary.players = summary.players as u8;
+                    }
+                }
+                None => {
+                    server.entity_mut(&summary.room).insert(RoomSummary::new(
+                        summary.max_players as u8,
+                        summary.players as u8,
+                        summary.variant.clone(),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn update_seats<'world, 'state>(
+    global: Res<Global>,
+    mut players: Query<(&mut Player, Option<&Owner>, Option<&Ready>)>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+) {
+    for (entity, seat, ready, owner) in global.lobby.seats() {
+        if let Ok((mut player, was_owner, was_ready)) = players.get_mut(entity) {
+            if *player.seat as usize != seat {
+                *player.seat = seat as u8;
+            }
+
+            match (ready, was_ready.is_some()) {
+                (true, false) => {
+                    server.entity_mut(&entity).insert(Ready::new());
+                }
+                (false, true) => {
+                    server.entity_mut(&entity).remove::<Ready>();
+                }
+                _ => (),
+            }
+
+            match (owner, was_owner.is_some()) {
+                (true, false) => {
+                    server.entity_mut(&entity).insert(Owner::new());
+                }
+                (false, true) => {
+                    server.entity_mut(&entity).remove::<Owner>();
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn update_scope<'world, 'state>(
+    global: Res<Global>,
+    online: Query<&Online>,
+    players: Query<&Player>,
+    rooms: Query<&Room>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    spectators: Query<&Spectator>,
+    users: Query<&User>,
+) {
+    let mut members = HashMap::new();
+    for player in players.iter() {
+        if let (Some(user), Some(room)) = (player.user.get(&server), player.room.get(&server)) {
+            members.insert(user, room);
+        }
+    }
+
+    for spectator in spectators.iter() {
+        if let (Some(user), Some(room)) = (spectator.user.get(&server), spectator.room.get(&server))
+        {
+            members.insert(user, room);
+        }
+    }
+
     for (_, user_key, entity) in server.scope_checks() {
-        server.user_scope(&user_key).include(&entity);
+        let viewer = match global.lobby.get_user(user_key) {
+            Some(user) => Viewer {
+                context: match global.lobby.room_of(user_key) {
+                    Some(room) => Context::Room(room),
+                    None => Context::Lobby,
+                },
+                user,
+            },
+            None => {
+                server.user_scope(&user_key).exclude(&entity);
+                continue;
+            }
+        };
+
+        let subject = if let Ok(player) = players.get(entity) {
+            player
+                .room
+                .get(&server)
+                .map_or(Subject::Other, Subject::Member)
+        } else if let Ok(spectator) = spectators.get(entity) {
+            spectator
+                .room
+                .get(&server)
+                .map_or(Subject::Other, Subject::Member)
+        } else if rooms.contains(entity) {
+            Subject::Room(entity)
+        } else if online.contains(entity) {
+            Subject::Online
+        } else if users.contains(entity) {
+            Subject::User(entity, members.get(&entity).copied())
+        } else {
+            Subject::Other
+        };
+
+        match viewer.sees(subject) {
+            true => server.user_scope(&user_key).include(&entity),
+            false => server.user_scope(&user_key).exclude(&entity),
+        };
     }
 }
 
 fn update_server(mut server: Server<Protocol, DefaultChannels>) {
     server.send_all_updates();
 }
+
+fn watch_room<'world, 'state>(
+    mut events: EventReader<MessageEvent<Protocol, DefaultChannels>>,
+    mut global: ResMut<Global>,
+    rooms: Query<&Room>,
+    mut server: Server<'world, 'state, Protocol, DefaultChannels>,
+    user_names: Query<&Name, With<User>>,
+) {
+    let global = &mut *global;
+    for event in events.iter() {
+        if let MessageEvent(user_key, _, Protocol::WatchRoom(msg)) = event {
+            if !global.limiter.admit(*user_key, &event.2) {
+                continue;
+            }
+
+            let room = msg.room.get(&server).filter(|room| rooms.contains(*room));
+            unlock(
+                global,
+                &mut server,
+                &user_names,
+                *user_key,
+                room,
+                true,
+                (*msg.password).as_deref(),
+            );
+        }
+    }
+}
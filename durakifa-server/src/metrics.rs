@@ -0,0 +1,275 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy_log::{error, info};
+use durakifa_protocol::protocol::{NameError, Rejection};
+
+pub const BANDWIDTH_WINDOW: Duration = Duration::from_secs(10);
+/// Scrapes are served one at a time, so a slow client must not hold up the others.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+const RENDER_INTERVAL: Duration = Duration::from_secs(1);
+/// Longer request lines are cut off, which is plenty for `GET /metrics`.
+const REQUEST_LEN_MAX: u64 = 1024;
+const TICK_QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+/// Ticks the quantiles are taken over, a minute at the default tick.
+const TICK_WINDOW: usize = 1200;
+
+pub struct Gauges {
+    pub games: usize,
+    pub rooms: usize,
+    pub sent: Vec<(String, f32)>,
+    pub users: usize,
+}
+
+pub struct Metrics {
+    /// Start of the current frame, a tick takes from there to the end of `Stage::Tick`.
+    frame: Instant,
+    messages: HashMap<&'static str, u64>,
+    /// Page handed out by the HTTP thread, `None` if the endpoint is off.
+    page: Option<Arc<Mutex<String>>>,
+    rejected: HashMap<(&'static str, &'static str), u64>,
+    rendered: Instant,
+    tick_count: u64,
+    tick_sum: f64,
+    ticks: VecDeque<f64>,
+}
+
+impl Metrics {
+    pub fn new(addr: Option<SocketAddr>) -> Self {
+        Metrics {
+            frame: Instant::now(),
+            messages: HashMap::new(),
+            page: addr.and_then(serve),
+            rejected: HashMap::new(),
+            rendered: Instant::now(),
+            tick_count: 0,
+            tick_sum: 0.0,
+            ticks: VecDeque::with_capacity(TICK_WINDOW),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.page.is_some()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.is_enabled() && self.rendered.elapsed() >= RENDER_INTERVAL
+    }
+
+    pub fn message(&mut self, kind: &'static str) {
+        *self.messages.entry(kind).or_insert(0) += 1;
+    }
+
+    pub fn reject(&mut self, action: &'static str, reason: &'static str) {
+        *self.rejected.entry((action, reason)).or_insert(0) += 1;
+    }
+
+    pub fn render(&mut self, gauges: Gauges, dropped: impl Iterator<Item = (&'static str, u64)>) {
+        let page = match &self.page {
+            Some(page) => page,
+            None => return,
+        };
+
+        let mut out = String::new();
+        gauge(&mut out, "durakifa_users", "Users online", gauges.users);
+        gauge(&mut out, "durakifa_rooms", "Open rooms", gauges.rooms);
+        gauge(
+            &mut out,
+            "durakifa_games",
+            "Rooms with a game in progress",
+            gauges.games,
+        );
+
+        header(
+            &mut out,
+            "durakifa_messages_total",
+            "Messages received per type",
+            "counter",
+        );
+        let mut messages = self.messages.iter().collect::<Vec<_>>();
+        messages.sort();
+        for (kind, count) in messages {
+            let _ = writeln!(
+                out,
+                "durakifa_messages_total{{type=\"{}\"}} {}",
+                kind, count
+            );
+        }
+
+        header(
+            &mut out,
+            "durakifa_rejected_total",
+            "Refused actions per action and reason",
+            "counter",
+        );
+        let mut rejected = self
+            .rejected
+            .iter()
+            .map(|(&key, &count)| (key, count))
+            .chain(dropped.map(|(kind, count)| (("rate_limit", kind), count)))
+            .collect::<Vec<_>>();
+        rejected.sort();
+        for ((action, reason), count) in rejected {
+            let _ = writeln!(
+                out,
+                "durakifa_rejected_total{{action=\"{}\",reason=\"{}\"}} {}",
+                action, reason, count
+            );
+        }
+
+        header(
+            &mut out,
+            "durakifa_tick_seconds",
+            "Time a server tick takes",
+            "summary",
+        );
+        let mut ticks = self.ticks.iter().copied().collect::<Vec<_>>();
+        ticks.sort_by(f64::total_cmp);
+        for quantile in TICK_QUANTILES {
+            let value = match ticks.is_empty() {
+                true => f64::NAN,
+                false => ticks[((ticks.len() - 1) as f64 * quantile).round() as usize],
+            };
+
+            let _ = writeln!(
+                out,
+                "durakifa_tick_seconds{{quantile=\"{}\"}} {}",
+                quantile, value
+            );
+        }
+
+        let _ = writeln!(out, "durakifa_tick_seconds_sum {}", self.tick_sum);
+        let _ = writeln!(out, "durakifa_tick_seconds_count {}", self.tick_count);
+
+        header(
+            &mut out,
+            "durakifa_user_sent_bytes_per_second",
+            "Bytes per second sent to each user",
+            "gauge",
+        );
+        for (name, sent) in gauges.sent {
+            let _ = writeln!(
+                out,
+                "durakifa_user_sent_bytes_per_second{{user=\"{}\"}} {}",
+                escape(&name),
+                sent
+            );
+        }
+
+        if let Ok(mut page) = page.lock() {
+            *page = out;
+        }
+
+        self.rendered = Instant::now();
+    }
+
+    pub fn start_frame(&mut self) {
+        self.frame = Instant::now();
+    }
+
+    pub fn tick(&mut self) {
+        let secs = self.frame.elapsed().as_secs_f64();
+        if self.ticks.len() == TICK_WINDOW {
+            self.ticks.pop_front();
+        }
+
+        self.ticks.push_back(secs);
+        self.tick_count += 1;
+        self.tick_sum += secs;
+    }
+}
+
+pub fn join_reason(reason: &Rejection) -> &'static str {
+    match reason {
+        Rejection::Banned => "banned",
+        Rejection::Failed => "failed",
+        Rejection::Full => "full",
+        Rejection::InProgress => "in_progress",
+        Rejection::NotFound => "not_found",
+        Rejection::Password => "password",
+    }
+}
+
+pub fn name_reason(reason: &NameError) -> &'static str {
+    match reason {
+        NameError::Characters => "characters",
+        NameError::Empty => "empty",
+        NameError::Taken => "taken",
+        NameError::TooLong => "too_long",
+    }
+}
+
+/// Label values are user names, which may contain quotes and backslashes.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: usize) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn serve(addr: SocketAddr) -> Option<Arc<Mutex<String>>> {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("failed to bind metrics endpoint {}: {}", addr, e);
+            return None;
+        }
+    };
+
+    info!("metrics endpoint listening on http://{}/metrics", addr);
+    let page = Arc::new(Mutex::new(String::new()));
+    let served = Arc::clone(&page);
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+                || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+            {
+                continue;
+            }
+
+            let mut request = String::new();
+            let mut reader = BufReader::new((&stream).take(REQUEST_LEN_MAX));
+            if reader.read_line(&mut request).is_err() {
+                continue;
+            }
+
+            let response = match request.split_whitespace().nth(1) {
+                Some("/metrics") => {
+                    let body = served.lock().map_or(String::new(), |page| page.clone());
+                    format!(
+                        "HTTP/1.1 200 OK\r\n\
+                        Content-Type: text/plain; version=0.0.4\r\n\
+                        Content-Length: {}\r\n\
+                        Connection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                }
+                _ => String::from(
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                ),
+            };
+
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    Some(page)
+}
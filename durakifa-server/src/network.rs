@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::Entity;
+use durakifa_protocol::protocol::Protocol;
+use naia_bevy_server::{shared::DefaultChannels, RoomKey, Server, UserKey};
+
+use crate::logic::lobby::Host;
+
+pub struct Network {
+    /// Everyone connected is in here, public rooms are listed in it.
+    pub lobby_key: RoomKey,
+    /// naia room of every room entity, along with the naia room the room entity is listed in.
+    rooms: HashMap<Entity, (RoomKey, RoomKey)>,
+}
+
+impl Network {
+    pub fn new<'world, 'state>(
+        server: &mut Server<'world, 'state, Protocol, DefaultChannels>,
+    ) -> Self {
+        Network {
+            lobby_key: server.make_room().key(),
+            rooms: HashMap::new(),
+        }
+    }
+
+    pub fn host<'a, 'world, 'state>(
+        &'a mut self,
+        server: &'a mut Server<'world, 'state, Protocol, DefaultChannels>,
+    ) -> NaiaHost<'a, 'world, 'state> {
+        NaiaHost {
+            network: self,
+            server,
+        }
+    }
+}
+
+pub struct NaiaHost<'a, 'world, 'state> {
+    network: &'a mut Network,
+    server: &'a mut Server<'world, 'state, Protocol, DefaultChannels>,
+}
+
+impl<'a, 'world, 'state> Host<UserKey> for NaiaHost<'a, 'world, 'state> {
+    fn create_room(&mut self, private: bool) -> Entity {
+        let room_key = self.server.make_room().key();
+        let listed_in = match private {
+            true => room_key,
+            false => self.network.lobby_key,
+        };
+
+        let room = self.server.spawn().enter_room(&listed_in).id();
+        self.network.rooms.insert(room, (room_key, listed_in));
+        room
+    }
+
+    fn despawn(&mut self, entity: Entity) {
+        self.server.entity_mut(&entity).despawn();
+    }
+
+    fn destroy_room(&mut self, room: Entity) {
+        if let Some((room_key, listed_in)) = self.network.rooms.remove(&room) {
+            self.server
+                .entity_mut(&room)
+                .leave_room(&listed_in)
+                .despawn();
+            self.server.room_mut(&room_key).destroy();
+        }
+    }
+
+    fn enter_lobby(&mut self, user_key: UserKey) {
+        self.server
+            .user_mut(&user_key)
+            .enter_room(&self.network.lobby_key);
+    }
+
+    fn enter_room(&mut self, user_key: UserKey, room: Entity) {
+        if let Some((room_key, _)) = self.network.rooms.get(&room) {
+            self.server.user_mut(&user_key).enter_room(room_key);
+        }
+    }
+
+    fn leave_room(&mut self, user_key: UserKey, room: Entity) {
+        // Expired users are long gone from the server
+        if !self.server.user_exists(&user_key) {
+            return;
+        }
+
+        if let Some((room_key, _)) = self.network.rooms.get(&room) {
+            self.server.user_mut(&user_key).leave_room(room_key);
+        }
+    }
+
+    fn spawn_member(&mut self, room: Entity) -> Entity {
+        let mut entity = self.server.spawn();
+        if let Some((room_key, _)) = self.network.rooms.get(&room) {
+            entity.enter_room(room_key);
+        }
+
+        entity.id()
+    }
+
+    fn spawn_user(&mut self) -> Entity {
+        self.server.spawn().enter_room(&self.network.lobby_key).id()
+    }
+}
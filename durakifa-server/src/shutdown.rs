@@ -0,0 +1,148 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::system::Resource;
+use bevy_log::error;
+
+use crate::logic::lobby::SavedRoom;
+
+/// Seconds left at which the users are reminded of the shutdown, besides when it starts.
+const NOTICES: [u64; 4] = [60, 30, 10, 5];
+
+#[derive(Resource)]
+pub struct Shutdown {
+    deadline: Option<Instant>,
+    finished: bool,
+    notified: Option<u64>,
+    signal: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let signal = Arc::new(AtomicBool::new(false));
+        on_signal(&signal);
+        Shutdown {
+            deadline: None,
+            finished: false,
+            notified: None,
+            signal,
+        }
+    }
+
+    pub fn begin(&mut self, after: Duration) -> bool {
+        if self.deadline.is_some() {
+            return false;
+        }
+
+        self.deadline = Some(Instant::now() + after);
+        true
+    }
+
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    pub fn is_due(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    pub fn notice(&mut self) -> Option<u64> {
+        let left = self.remaining()?.as_secs_f32().ceil() as u64;
+        let due = match self.notified {
+            Some(notified) => NOTICES.iter().any(|&at| left <= at && notified > at),
+            None => true,
+        };
+
+        if !due {
+            return None;
+        }
+
+        self.notified = Some(left);
+        Some(left)
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn take_signal(&self) -> bool {
+        self.signal.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Called once every restored game is settled, so the games are not restored again.
+pub fn discard(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        error!("failed to remove saved games at {}: {}", path.display(), e);
+    }
+}
+
+/// The file stays until every game in it is settled, see `discard`, so a crash in the
+/// meantime loses nothing.
+pub fn load(path: &Path) -> Vec<SavedRoom> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+
+    match serde_json::from_str(&json) {
+        Ok(rooms) => rooms,
+        // The file stays around for a look at what went wrong
+        Err(e) => {
+            error!("failed to read saved games from {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Only the server may read the file, the session tokens in it let anyone take a seat.
+pub fn save(path: &Path, rooms: &[SavedRoom]) -> io::Result<()> {
+    create_private(path)?.write_all(serde_json::to_string_pretty(rooms)?.as_bytes())
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .mode(0o600)
+        .truncate(true)
+        .write(true)
+        .open(path)?;
+    // The mode only applies to new files
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> io::Result<File> {
+    File::create(path)
+}
+
+#[cfg(unix)]
+fn on_signal(signal: &Arc<AtomicBool>) {
+    if let Err(e) = signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(signal)) {
+        error!("failed to register SIGTERM: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn on_signal(_signal: &Arc<AtomicBool>) {}
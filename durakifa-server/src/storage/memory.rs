@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use super::{GameResult, Profile, Stats, Storage};
+
+/// Keeps everything in memory, for tests and servers without a database.
+pub struct MemoryStorage {
+    games: Vec<GameResult>,
+    profiles: HashMap<String, Profile>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage {
+            games: Vec::new(),
+            profiles: HashMap::new(),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn insert_profile(&mut self, profile: Profile) -> bool {
+        let key = profile.name.to_lowercase();
+        if self.profiles.contains_key(&key) {
+            return false;
+        }
+
+        self.profiles.insert(key, profile);
+        true
+    }
+
+    fn leaderboard(&self, count: usize) -> Vec<(String, f32)> {
+        let mut ratings = self
+            .profiles
+            .values()
+            .map(|profile| (profile.name.clone(), profile.rating))
+            .collect::<Vec<_>>();
+
+        ratings.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ratings.truncate(count);
+        ratings
+    }
+
+    fn profile(&self, name: &str) -> Option<Profile> {
+        self.profiles
+            .get(&name.to_lowercase())
+            .map(|profile| Profile {
+                name: profile.name.clone(),
+                password: profile.password.clone(),
+                rating: profile.rating,
+            })
+    }
+
+    fn rank(&self, name: &str) -> Option<u32> {
+        let rating = self.profiles.get(&name.to_lowercase())?.rating;
+        let better = self.profiles.values().filter(|p| p.rating > rating).count();
+        Some(better as u32 + 1)
+    }
+
+    fn record_game(&mut self, mut game: GameResult) -> Option<u64> {
+        game.players
+            .retain(|player| self.profiles.contains_key(&player.name.to_lowercase()));
+        self.games.push(game);
+        Some(self.games.len() as u64)
+    }
+
+    fn replay(&self, game: u64) -> Option<Vec<String>> {
+        let index = usize::try_from(game).ok()?.checked_sub(1)?;
+        self.games.get(index).map(|game| game.replay.clone())
+    }
+
+    fn set_rating(&mut self, name: &str, rating: f32) {
+        if let Some(profile) = self.profiles.get_mut(&name.to_lowercase()) {
+            profile.rating = rating;
+        }
+    }
+
+    fn stats(&self, name: &str) -> Stats {
+        let name = name.to_lowercase();
+        Stats::from_results(self.games.iter().filter_map(|game| {
+            game.players
+                .iter()
+                .find(|player| player.name.to_lowercase() == name)
+                .map(|player| (player.durak, player.cards_taken))
+        }))
+    }
+}
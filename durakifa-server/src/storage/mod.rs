@@ -0,0 +1,180 @@
+mod memory;
+mod sqlite;
+
+pub use self::{memory::MemoryStorage, sqlite::SqliteStorage};
+
+/// A finished game, `players` in the order they got rid of their cards.
+pub struct GameResult {
+    pub players: Vec<Placement>,
+    /// Moves in the order they were made, one line each.
+    pub replay: Vec<String>,
+}
+
+pub struct Placement {
+    /// `None` if the game ended before anyone counted, e.g. because players walked out.
+    pub cards_taken: Option<u32>,
+    pub durak: bool,
+    pub name: String,
+}
+
+pub struct Profile {
+    pub name: String,
+    pub password: String,
+    pub rating: f32,
+}
+
+/// A game counts as won for every player that did not end up as durak.
+#[derive(Default)]
+pub struct Stats {
+    /// Over the games that counted the cards taken, `None` if there are none.
+    pub cards_taken_avg: Option<f32>,
+    pub duraks: u32,
+    pub games: u32,
+    pub win_streak: u32,
+}
+
+impl Stats {
+    /// Builds the statistics from `(durak, cards_taken)` pairs, oldest game first.
+    fn from_results(results: impl Iterator<Item = (bool, Option<u32>)>) -> Self {
+        let mut stats = Stats::default();
+        let mut cards_taken = Vec::new();
+        let mut streak = 0;
+        for (durak, taken) in results {
+            stats.games += 1;
+            cards_taken.extend(taken);
+            if durak {
+                stats.duraks += 1;
+                streak = 0;
+            } else {
+                streak += 1;
+                stats.win_streak = stats.win_streak.max(streak);
+            }
+        }
+
+        if !cards_taken.is_empty() {
+            let sum = cards_taken.iter().sum::<u32>();
+            stats.cards_taken_avg = Some(sum as f32 / cards_taken.len() as f32);
+        }
+
+        stats
+    }
+}
+
+/// Everything that has to survive a restart. Names are compared regardless of their case.
+pub trait Storage: Send + Sync {
+    /// Stores a new profile, returns `false` if the name is taken.
+    fn insert_profile(&mut self, profile: Profile) -> bool;
+
+    /// Returns the `count` best rated profiles, best first.
+    fn leaderboard(&self, count: usize) -> Vec<(String, f32)>;
+
+    fn profile(&self, name: &str) -> Option<Profile>;
+
+    /// Returns the position of `name` on the leaderboard, starting at 1.
+    fn rank(&self, name: &str) -> Option<u32>;
+
+    /// Stores a finished game and returns its id. Only players with a profile get a result,
+    /// or the games of a guest would pass on to whoever signs up with its name later.
+    fn record_game(&mut self, game: GameResult) -> Option<u64>;
+
+    /// Returns the moves of the game with the given id.
+    fn replay(&self, game: u64) -> Option<Vec<String>>;
+
+    fn set_rating(&mut self, name: &str, rating: f32);
+
+    fn stats(&self, name: &str) -> Stats;
+}
+
+/// Builds a finished game of `players` in the order they got rid of their cards.
+#[cfg(test)]
+pub fn game(durak: &str, players: &[&str]) -> GameResult {
+    GameResult {
+        players: players
+            .iter()
+            .map(|name| Placement {
+                cards_taken: None,
+                durak: *name == durak,
+                name: name.to_string(),
+            })
+            .collect(),
+        replay: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Storages that know the profiles `a`, `b` and `c`.
+    fn backends() -> Vec<Box<dyn Storage>> {
+        let mut backends: Vec<Box<dyn Storage>> = vec![
+            Box::new(MemoryStorage::new()),
+            Box::new(SqliteStorage::open(Path::new(":memory:")).unwrap()),
+        ];
+
+        for storage in backends.iter_mut() {
+            for name in ["a", "b", "c"] {
+                storage.insert_profile(profile(name));
+            }
+        }
+
+        backends
+    }
+
+    fn profile(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            password: String::new(),
+            rating: 1500.0,
+        }
+    }
+
+    #[test]
+    fn guests_pass_no_games_on() {
+        for mut storage in backends() {
+            storage.record_game(game("g", &["a", "g"]));
+            assert!(storage.insert_profile(profile("G")));
+            assert_eq!(storage.stats("g").games, 0);
+            assert_eq!(storage.stats("a").games, 1);
+        }
+    }
+
+    #[test]
+    fn stats_add_up_the_games_of_a_player() {
+        for mut storage in backends() {
+            storage.record_game(game("b", &["A", "b"]));
+            storage.record_game(game("c", &["a", "c"]));
+            storage.record_game(game("a", &["b", "a"]));
+            storage.record_game(game("b", &["a", "b"]));
+
+            let stats = storage.stats("a");
+            assert_eq!(stats.games, 4);
+            assert_eq!(stats.duraks, 1);
+            assert_eq!(stats.win_streak, 2);
+            assert_eq!(stats.cards_taken_avg, None);
+            assert_eq!(storage.stats("nobody").games, 0);
+        }
+    }
+
+    #[test]
+    fn replays_and_cards_taken_are_kept() {
+        for mut storage in backends() {
+            let mut counted = game("b", &["a", "b"]);
+            counted.players[0].cards_taken = Some(2);
+            counted.players[1].cards_taken = Some(9);
+            counted.replay = vec![String::from("a 6S"), String::from("b 7S")];
+            let id = storage.record_game(counted).unwrap();
+            storage.record_game(game("a", &["b", "a"]));
+            storage.record_game(game("b", &["a", "b"]));
+
+            assert_eq!(storage.replay(id).unwrap(), ["a 6S", "b 7S"]);
+            assert!(storage.replay(id + 1).unwrap().is_empty());
+            assert!(storage.replay(id + 10).is_none());
+            assert_eq!(storage.stats("a").cards_taken_avg, Some(2.0));
+            assert_eq!(storage.stats("b").games, 3);
+            assert_eq!(storage.stats("b").cards_taken_avg, Some(9.0));
+        }
+    }
+}
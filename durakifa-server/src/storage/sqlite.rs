@@ -0,0 +1,272 @@
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy_log::error;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{GameResult, Profile, Stats, Storage};
+
+/// Applied in order, `PRAGMA user_version` counts the ones that already ran.
+const MIGRATIONS: [&str; 3] = [
+    "
+    CREATE TABLE IF NOT EXISTS profiles (
+        key TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        password TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS games (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        finished INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        game INTEGER NOT NULL REFERENCES games(id),
+        key TEXT NOT NULL,
+        place INTEGER NOT NULL,
+        durak INTEGER NOT NULL,
+        PRIMARY KEY (game, key)
+    );
+    ",
+    "ALTER TABLE profiles ADD COLUMN rating REAL NOT NULL DEFAULT 1500;",
+    "
+    ALTER TABLE games ADD COLUMN replay TEXT NOT NULL DEFAULT '';
+    ALTER TABLE results ADD COLUMN cards_taken INTEGER;
+    ",
+];
+
+/// Stores everything in a SQLite file. The connection sits behind a mutex, since bevy
+/// resources have to be `Sync`.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            connection.execute_batch(migration)?;
+            connection.pragma_update(None, "user_version", i + 1)?;
+        }
+
+        Ok(SqliteStorage {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn leaderboard(connection: &Connection, count: usize) -> rusqlite::Result<Vec<(String, f32)>> {
+        let mut stmt = connection
+            .prepare("SELECT name, rating FROM profiles ORDER BY rating DESC LIMIT ?1")?;
+        let rows = stmt.query_map(params![count], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    fn rank(connection: &Connection, name: &str) -> rusqlite::Result<Option<u32>> {
+        let rating: Option<f32> = connection
+            .query_row(
+                "SELECT rating FROM profiles WHERE key = ?1",
+                params![name.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match rating {
+            Some(rating) => connection
+                .query_row(
+                    "SELECT COUNT(*) + 1 FROM profiles WHERE rating > ?1",
+                    params![rating],
+                    |row| row.get(0),
+                )
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn record(connection: &mut Connection, game: &GameResult) -> rusqlite::Result<u64> {
+        let finished = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let tx = connection.transaction()?;
+        tx.execute(
+            "INSERT INTO games (finished, replay) VALUES (?1, ?2)",
+            params![finished, game.replay.join("\n")],
+        )?;
+
+        let id = tx.last_insert_rowid();
+        for (place, player) in game.players.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO results (game, key, place, durak, cards_taken)
+                SELECT ?1, ?2, ?3, ?4, ?5 WHERE EXISTS (SELECT 1 FROM profiles WHERE key = ?2)",
+                params![
+                    id,
+                    player.name.to_lowercase(),
+                    place,
+                    player.durak,
+                    player.cards_taken
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(id as u64)
+    }
+
+    fn results(connection: &Connection, name: &str) -> rusqlite::Result<Vec<(bool, Option<u32>)>> {
+        let mut stmt = connection
+            .prepare("SELECT durak, cards_taken FROM results WHERE key = ?1 ORDER BY game")?;
+        let rows = stmt.query_map(params![name.to_lowercase()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+
+        rows.collect()
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn insert_profile(&mut self, profile: Profile) -> bool {
+        let connection = self.connection.get_mut().unwrap();
+        match connection.execute(
+            "INSERT OR IGNORE INTO profiles (key, name, password, rating) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                profile.name.to_lowercase(),
+                profile.name,
+                profile.password,
+                profile.rating
+            ],
+        ) {
+            Ok(rows) => rows > 0,
+            Err(e) => {
+                error!("failed to store profile {}: {}", profile.name, e);
+                false
+            }
+        }
+    }
+
+    fn leaderboard(&self, count: usize) -> Vec<(String, f32)> {
+        let connection = self.connection.lock().unwrap();
+        SqliteStorage::leaderboard(&connection, count).unwrap_or_else(|e| {
+            error!("failed to load leaderboard: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn profile(&self, name: &str) -> Option<Profile> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row(
+                "SELECT name, password, rating FROM profiles WHERE key = ?1",
+                params![name.to_lowercase()],
+                |row| {
+                    Ok(Profile {
+                        name: row.get(0)?,
+                        password: row.get(1)?,
+                        rating: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                error!("failed to load profile {}: {}", name, e);
+                None
+            })
+    }
+
+    fn rank(&self, name: &str) -> Option<u32> {
+        let connection = self.connection.lock().unwrap();
+        SqliteStorage::rank(&connection, name).unwrap_or_else(|e| {
+            error!("failed to load rank of {}: {}", name, e);
+            None
+        })
+    }
+
+    fn record_game(&mut self, game: GameResult) -> Option<u64> {
+        let connection = self.connection.get_mut().unwrap();
+        match SqliteStorage::record(connection, &game) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                error!("failed to store game: {}", e);
+                None
+            }
+        }
+    }
+
+    fn replay(&self, game: u64) -> Option<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row(
+                "SELECT replay FROM games WHERE id = ?1",
+                params![game],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                error!("failed to load replay {}: {}", game, e);
+                None
+            })
+            .map(|replay| replay.lines().map(String::from).collect())
+    }
+
+    fn set_rating(&mut self, name: &str, rating: f32) {
+        let connection = self.connection.get_mut().unwrap();
+        if let Err(e) = connection.execute(
+            "UPDATE profiles SET rating = ?2 WHERE key = ?1",
+            params![name.to_lowercase(), rating],
+        ) {
+            error!("failed to store rating of {}: {}", name, e);
+        }
+    }
+
+    fn stats(&self, name: &str) -> Stats {
+        let connection = self.connection.lock().unwrap();
+        match SqliteStorage::results(&connection, name) {
+            Ok(results) => Stats::from_results(results.into_iter()),
+            Err(e) => {
+                error!("failed to load stats of {}: {}", name, e);
+                Stats::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn migrations_upgrade_old_databases() {
+        let path = env::temp_dir().join(format!("durakifa-migrations-{}.sqlite", process::id()));
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection.execute_batch(MIGRATIONS[0]).unwrap();
+            connection.pragma_update(None, "user_version", 1).unwrap();
+            connection
+                .execute(
+                    "INSERT INTO profiles (key, name, password) VALUES ('a', 'A', 'hash')",
+                    [],
+                )
+                .unwrap();
+        }
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        let profile = storage.profile("a").unwrap();
+        assert_eq!(profile.name, "A");
+        assert_eq!(profile.rating, 1500.0);
+
+        let version: usize = storage
+            .connection
+            .lock()
+            .unwrap()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+}